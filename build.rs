@@ -0,0 +1,26 @@
+// build.rs - Generate mayaqua.h into OUT_DIR for the C ABI (feature "ffi")
+//
+// The copy in include/ is committed and only updated on purpose:
+//   cbindgen --config cbindgen.toml --crate mayaqua --output include/mayaqua.h
+// tests/ffi_test.rs fails when it differs from the header generated here.
+
+fn main() {
+    #[cfg(feature = "ffi")]
+    generate_header();
+}
+
+#[cfg(feature = "ffi")]
+fn generate_header() {
+    let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out = std::env::var("OUT_DIR").unwrap();
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", dir)).expect("cbindgen.toml");
+    cbindgen::Builder::new()
+        .with_crate(&dir)
+        .with_config(config)
+        .generate()
+        .expect("Unable to generate the C header")
+        .write_to_file(format!("{}/mayaqua.h", out));
+}
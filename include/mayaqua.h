@@ -0,0 +1,160 @@
+#ifndef MAYAQUA_H
+#define MAYAQUA_H
+
+/* Generated by cbindgen from src/ffi.rs - do not edit */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// Result code of every fallible mayaqua_* function
+typedef enum {
+  MAYAQUA_ERROR_OK = 0,
+  // A required pointer argument was NULL
+  MAYAQUA_ERROR_NULL_POINTER = 1,
+  // A string is not valid UTF-8, or contains an interior NUL
+  MAYAQUA_ERROR_INVALID_STRING = 2,
+  // No element with that name
+  MAYAQUA_ERROR_NOT_FOUND = 3,
+  // The element exists but holds another type
+  MAYAQUA_ERROR_WRONG_TYPE = 4,
+  MAYAQUA_ERROR_NUMBER_EXCEEDS = 5,
+  MAYAQUA_ERROR_SIZE_OVER = 6,
+  MAYAQUA_ERROR_INVALID_TYPE = 7,
+  MAYAQUA_ERROR_SAME_NAME_EXISTS = 8,
+  MAYAQUA_ERROR_ZERO_NUM_VALUE = 9,
+  MAYAQUA_ERROR_INVALID_NAME = 10,
+  // The input ended early or could not be read
+  MAYAQUA_ERROR_IO = 11,
+} MayaquaError;
+
+typedef struct MayaquaPack MayaquaPack;
+
+// Create an empty pack. Release it with mayaqua_pack_free.
+MayaquaPack *mayaqua_pack_new(void);
+
+// Release a pack. NULL is ignored.
+//
+// # Safety
+// `p` must be NULL or a pack from mayaqua_pack_new / mayaqua_read_pack that
+// has not been freed yet.
+void mayaqua_pack_free(MayaquaPack *p);
+
+// Number of elements in the pack, 0 for NULL.
+//
+// # Safety
+// `p` must be NULL or a valid pack.
+uint32_t mayaqua_pack_get_num(const MayaquaPack *p);
+
+// Add an Int element.
+//
+// # Safety
+// `p` must be a valid pack and `name` a NUL-terminated string.
+MayaquaError mayaqua_pack_add_int(MayaquaPack *p, const char *name, uint32_t value);
+
+// Add an Int64 element.
+//
+// # Safety
+// `p` must be a valid pack and `name` a NUL-terminated string.
+MayaquaError mayaqua_pack_add_int64(MayaquaPack *p, const char *name, uint64_t value);
+
+// Add a bool, sent as an Int element of 0 or 1.
+//
+// # Safety
+// `p` must be a valid pack and `name` a NUL-terminated string.
+MayaquaError mayaqua_pack_add_bool(MayaquaPack *p, const char *name, bool value);
+
+// Add a Str element; `value` must be UTF-8.
+//
+// # Safety
+// `p` must be a valid pack, `name` and `value` NUL-terminated strings.
+MayaquaError mayaqua_pack_add_str(MayaquaPack *p, const char *name, const char *value);
+
+// Add a UniStr element; `value` must be UTF-8.
+//
+// # Safety
+// `p` must be a valid pack, `name` and `value` NUL-terminated strings.
+MayaquaError mayaqua_pack_add_uni_str(MayaquaPack *p, const char *name, const char *value);
+
+// Add a Data element holding a copy of `len` bytes at `data`.
+//
+// # Safety
+// `p` must be a valid pack, `name` a NUL-terminated string and `data` valid
+// for `len` bytes (it may be NULL when `len` is 0).
+MayaquaError mayaqua_pack_add_data(MayaquaPack *p,
+                                   const char *name,
+                                   const uint8_t *data,
+                                   size_t len);
+
+// Read the first value of an Int element.
+//
+// # Safety
+// `p` must be a valid pack, `name` a NUL-terminated string and `out` writable.
+MayaquaError mayaqua_pack_get_int(const MayaquaPack *p, const char *name, uint32_t *out);
+
+// Read the first value of an Int64 element.
+//
+// # Safety
+// `p` must be a valid pack, `name` a NUL-terminated string and `out` writable.
+MayaquaError mayaqua_pack_get_int64(const MayaquaPack *p, const char *name, uint64_t *out);
+
+// Read an Int element as a bool (non-zero is true).
+//
+// # Safety
+// `p` must be a valid pack, `name` a NUL-terminated string and `out` writable.
+MayaquaError mayaqua_pack_get_bool(const MayaquaPack *p, const char *name, bool *out);
+
+// Copy a Str element into a new string. Release it with mayaqua_string_free.
+//
+// # Safety
+// `p` must be a valid pack, `name` a NUL-terminated string and `out` writable.
+MayaquaError mayaqua_pack_get_str(const MayaquaPack *p, const char *name, char **out);
+
+// Copy a UniStr element into a new UTF-8 string. Release it with
+// mayaqua_string_free.
+//
+// # Safety
+// `p` must be a valid pack, `name` a NUL-terminated string and `out` writable.
+MayaquaError mayaqua_pack_get_uni_str(const MayaquaPack *p, const char *name, char **out);
+
+// Copy a Data element into a new buffer. Release it with mayaqua_bytes_free.
+//
+// # Safety
+// `p` must be a valid pack, `name` a NUL-terminated string, `out_data` and
+// `out_len` writable.
+MayaquaError mayaqua_pack_get_data(const MayaquaPack *p,
+                                   const char *name,
+                                   uint8_t **out_data,
+                                   size_t *out_len);
+
+// Serialize the pack, rejecting anything a SoftEther peer would refuse.
+// Release the buffer with mayaqua_bytes_free.
+//
+// # Safety
+// `p` must be a valid pack, `out_data` and `out_len` writable.
+MayaquaError mayaqua_pack_to_buf(const MayaquaPack *p, uint8_t **out_data, size_t *out_len);
+
+// Parse a serialized pack. Release it with mayaqua_pack_free.
+//
+// # Safety
+// `data` must be valid for `len` bytes and `out` writable.
+MayaquaError mayaqua_read_pack(const uint8_t *data, size_t len, MayaquaPack **out);
+
+// Release a string returned by this library. NULL is ignored.
+//
+// # Safety
+// `s` must be NULL or a string from mayaqua_pack_get_str / _get_uni_str.
+void mayaqua_string_free(char *s);
+
+// Release a buffer returned by this library. NULL is ignored.
+//
+// # Safety
+// `data` must be NULL or a buffer from mayaqua_pack_get_data /
+// mayaqua_pack_to_buf, and `len` the length returned with it.
+void mayaqua_bytes_free(uint8_t *data, size_t len);
+
+// A static description of an error code; never freed. Codes that are not
+// a MayaquaError give "Unknown error".
+const char *mayaqua_error_string(uint32_t code);
+
+#endif  /* MAYAQUA_H */
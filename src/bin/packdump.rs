@@ -0,0 +1,324 @@
+// packdump.rs - Decode and encode SoftEther packs for debugging
+//
+// Decoding reads a binary pack (or its hex dump) and prints a table of
+// elements, or the PackToJson form with --json. Encoding compiles the JSON
+// form, or a line-based text description, back into a binary pack.
+
+use anyhow::{anyhow, bail, Context, Result};
+use mayaqua::pack_json::{json_str_to_time64, time64_to_json_str};
+use mayaqua::*;
+use std::io::{Cursor, Read, Write};
+use std::net::IpAddr;
+
+const USAGE: &str = "\
+usage: packdump decode [--hex] [--json] [--bool NAME,..] [--time NAME,..] [FILE]
+       packdump encode [--json] [--hex] [-o OUT] [FILE]
+
+FILE defaults to stdin. decode --hex reads a hex dump instead of raw bytes;
+--bool and --time mark elements to render as bools or date-times, since packs
+read off the wire carry no hints. encode --hex writes a hex dump.
+
+Text input for encode has one value per line, `name type value`, where type is
+int, int64, bool, str, unistr, data (hex), ip or time (ISO-8601). Repeating a
+name adds another value to the same array element. Lines starting with # are
+ignored, and str/unistr values may be JSON-quoted to keep surrounding spaces.";
+
+// Data values longer than this are truncated in the table
+const DATA_PREVIEW_LEN: usize = 32;
+
+struct Options {
+    hex: bool,
+    json: bool,
+    bools: Vec<String>,
+    times: Vec<String>,
+    output: Option<String>,
+    input: Option<String>,
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("packdump: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let command = args.next().unwrap_or_default();
+    let opts = parse_options(args)?;
+
+    match command.as_str() {
+        "decode" => decode(&opts),
+        "encode" => encode(&opts),
+        "-h" | "--help" | "help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => bail!("{}", USAGE),
+    }
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options> {
+    let mut opts = Options {
+        hex: false,
+        json: false,
+        bools: Vec::new(),
+        times: Vec::new(),
+        output: None,
+        input: None,
+    };
+    let split = |s: String| -> Vec<String> { s.split(',').map(|n| n.trim().to_string()).collect() };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--hex" => opts.hex = true,
+            "--json" => opts.json = true,
+            "--bool" => opts.bools.extend(split(args.next().context("--bool needs names")?)),
+            "--time" => opts.times.extend(split(args.next().context("--time needs names")?)),
+            "-o" | "--output" => opts.output = Some(args.next().context("-o needs a path")?),
+            "-" => opts.input = None,
+            _ if arg.starts_with('-') => bail!("unknown option {}\n\n{}", arg, USAGE),
+            _ => opts.input = Some(arg),
+        }
+    }
+    Ok(opts)
+}
+
+fn read_input(path: &Option<String>) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    match path {
+        Some(path) => std::fs::File::open(path)
+            .and_then(|mut f| f.read_to_end(&mut buf))
+            .with_context(|| format!("reading {}", path))?,
+        None => std::io::stdin().read_to_end(&mut buf).context("reading stdin")?,
+    };
+    Ok(buf)
+}
+
+fn decode(opts: &Options) -> Result<()> {
+    let mut input = read_input(&opts.input)?;
+    if opts.hex {
+        input = hex_decode(std::str::from_utf8(&input).context("hex input is not text")?)?;
+    }
+
+    let mut pack = read_pack(&mut Cursor::new(&input)).map_err(|e| anyhow!("invalid pack: {}", e))?;
+    for (names, is_time) in [(&opts.bools, false), (&opts.times, true)] {
+        for name in names {
+            if let Some(e) = pack.get_element(name, None) {
+                let mut e = e.clone();
+                e.json_hint_is_bool |= !is_time;
+                e.json_hint_is_date_time |= is_time;
+                pack.replace(e).map_err(|e| anyhow!("{}", e))?;
+            }
+        }
+    }
+
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&pack_to_json(&pack))?);
+    } else {
+        print_table(&pack);
+    }
+    Ok(())
+}
+
+fn print_table(pack: &Pack) {
+    let rows: Vec<(String, &str, String)> = pack
+        .iter()
+        .filter(|e| !is_ip_companion(pack, &e.name))
+        .map(|e| {
+            let is_ip = e.type_ == ValueType::Int && is_ip_base(pack, &e.name);
+            let type_name = if is_ip { "ip" } else { type_name(e) };
+            let values: Vec<String> = (0..e.num_value() as u32)
+                .map(|i| {
+                    if is_ip {
+                        format_ip(pack.get_ip_addr_with_scope_ex(&e.name, i))
+                    } else {
+                        format_value(e, i)
+                    }
+                })
+                .collect();
+            (e.name.clone(), type_name, values.join(", "))
+        })
+        .collect();
+
+    let name_width = rows.iter().map(|r| r.0.len()).chain([4]).max().unwrap_or(4);
+    println!("{:<name_width$}  {:<7}  VALUE", "NAME", "TYPE");
+    for (name, type_name, value) in rows {
+        println!("{:<name_width$}  {:<7}  {}", name, type_name, value);
+    }
+}
+
+fn is_ip_base(pack: &Pack, name: &str) -> bool {
+    !name.contains('@') && pack.get_element(&format!("{}@ipv6_bool", name), Some(ValueType::Int)).is_some()
+}
+
+fn is_ip_companion(pack: &Pack, name: &str) -> bool {
+    ["@ipv6_bool", "@ipv6_array", "@ipv6_scope_id"]
+        .iter()
+        .filter_map(|s| name.strip_suffix(s))
+        .any(|base| pack.get_element(base, Some(ValueType::Int)).is_some())
+}
+
+fn type_name(e: &Element) -> &'static str {
+    match e.type_ {
+        ValueType::Int if e.json_hint_is_bool => "bool",
+        ValueType::Int => "int",
+        ValueType::Int64 if e.json_hint_is_date_time => "time",
+        ValueType::Int64 => "int64",
+        ValueType::Data => "data",
+        ValueType::Str => "str",
+        ValueType::UniStr => "unistr",
+    }
+}
+
+fn format_value(e: &Element, index: u32) -> String {
+    match &e.values[index as usize] {
+        Value::Int(v) if e.json_hint_is_bool => (*v != 0).to_string(),
+        Value::Int(v) => v.to_string(),
+        Value::Int64(v) if e.json_hint_is_date_time => time64_to_json_str(*v),
+        Value::Int64(v) => v.to_string(),
+        Value::Str(s) | Value::UniStr(s) => format!("{:?}", s),
+        Value::Data(d) if d.len() > DATA_PREVIEW_LEN => {
+            format!("{}... ({} bytes)", hex_encode(&d[..DATA_PREVIEW_LEN]), d.len())
+        }
+        Value::Data(d) => hex_encode(d),
+    }
+}
+
+fn format_ip(ip: Option<(IpAddr, u32)>) -> String {
+    match ip {
+        Some((ip @ IpAddr::V6(_), scope_id)) if scope_id != 0 => format!("{}%{}", ip, scope_id),
+        Some((ip, _)) => ip.to_string(),
+        None => "?".to_string(),
+    }
+}
+
+fn encode(opts: &Options) -> Result<()> {
+    let input = read_input(&opts.input)?;
+    let text = std::str::from_utf8(&input).context("input is not UTF-8")?;
+
+    let pack = if opts.json || text.trim_start().starts_with('{') {
+        let v: serde_json::Value = serde_json::from_str(text).context("parsing JSON")?;
+        json_to_pack(&v).map_err(|e| anyhow!("invalid pack JSON: {}", e))?
+    } else {
+        parse_text(text)?
+    };
+
+    let buf = pack.to_buf().map_err(|e| anyhow!("encoding pack: {}", e))?;
+    let out = if opts.hex { format!("{}\n", hex_encode(&buf)).into_bytes() } else { buf };
+    match &opts.output {
+        Some(path) => std::fs::write(path, out).with_context(|| format!("writing {}", path))?,
+        None => std::io::stdout().write_all(&out)?,
+    }
+    Ok(())
+}
+
+// Lines are grouped by name first, so repeated names become one array element
+fn parse_text(text: &str) -> Result<Pack> {
+    let mut elements: Vec<(String, String, Vec<String>)> = Vec::new();
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // Columns may be aligned with any run of spaces or tabs
+        let mut tokens = line.split_whitespace();
+        let (Some(name), Some(type_name)) = (tokens.next(), tokens.next()) else {
+            bail!("line {}: expected `name type value`", n + 1);
+        };
+        // The value is the rest of the line, with the whitespace inside it kept
+        let value = line[name.len()..].trim_start()[type_name.len()..].trim_start().to_string();
+
+        match elements.iter_mut().find(|(en, _, _)| en == name) {
+            Some((_, et, values)) if et.eq_ignore_ascii_case(type_name) => values.push(value),
+            Some(_) => bail!("line {}: {} was declared with another type", n + 1, name),
+            None => elements.push((name.to_string(), type_name.to_string(), vec![value])),
+        }
+    }
+
+    let mut pack = Pack::new();
+    for (name, type_name, values) in &elements {
+        let total = values.len() as u32;
+        for (i, value) in values.iter().enumerate() {
+            add_text_value(&mut pack, name, type_name, value, i as u32, total)
+                .with_context(|| format!("{} {} {}", name, type_name, value))?;
+        }
+    }
+    Ok(pack)
+}
+
+fn add_text_value(pack: &mut Pack, name: &str, type_name: &str, value: &str, index: u32, total: u32) -> Result<()> {
+    // A single value is added as a plain element, several as an array
+    let single = total == 1;
+    let added = match type_name.to_ascii_lowercase().as_str() {
+        "int" => {
+            let v = value.parse()?;
+            if single { pack.add_int(name, v) } else { pack.add_int_ex(name, v, index, total) }
+        }
+        "int64" => {
+            let v = value.parse()?;
+            if single { pack.add_int64(name, v) } else { pack.add_int64_ex(name, v, index, total) }
+        }
+        "bool" => {
+            let v = matches!(value.to_ascii_lowercase().as_str(), "true" | "yes" | "1");
+            if single { pack.add_bool(name, v) } else { pack.add_bool_ex(name, v, index, total) }
+        }
+        "time" => {
+            let t = json_str_to_time64(value).context("expected YYYY-MM-DDTHH:MM:SS[.mmm]Z")?;
+            let t = time64_to_system_time(t).context("time out of range")?;
+            if single { pack.add_time64(name, t) } else { pack.add_time64_ex(name, t, index, total) }
+        }
+        "str" => {
+            let v = unquote(value)?;
+            if single { pack.add_str(name, &v) } else { pack.add_str_ex(name, &v, index, total) }
+        }
+        "unistr" => {
+            let v = unquote(value)?;
+            if single { pack.add_uni_str(name, &v) } else { pack.add_uni_str_ex(name, &v, index, total) }
+        }
+        "data" => {
+            let v = hex_decode(value)?;
+            if single { pack.add_data(name, v) } else { pack.add_data_ex(name, v, index, total) }
+        }
+        "ip" => {
+            let (ip, scope_id) = match value.split_once('%') {
+                Some((addr, scope)) => (addr.parse()?, scope.parse()?),
+                None => (value.parse()?, 0),
+            };
+            if single {
+                pack.add_ip_addr_with_scope(name, ip, scope_id)
+            } else {
+                pack.add_ip_addr_with_scope_ex(name, ip, scope_id, index, total)
+            }
+        }
+        other => bail!("unknown type {}", other),
+    };
+    added.map(|_| ()).ok_or_else(|| anyhow!("could not add element (duplicate name?)"))
+}
+
+fn unquote(value: &str) -> Result<String> {
+    if value.starts_with('"') {
+        Ok(serde_json::from_str(value).context("bad quoted string")?)
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+fn hex_encode(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_decode(s: &str) -> Result<Vec<u8>> {
+    let digits: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        bail!("odd number of hex digits");
+    }
+    digits
+        .chunks(2)
+        .map(|c| {
+            let s = std::str::from_utf8(c)?;
+            u8::from_str_radix(s, 16).with_context(|| format!("bad hex byte {:?}", s))
+        })
+        .collect()
+}
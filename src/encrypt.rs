@@ -9,32 +9,29 @@ pub const SHA512_SIZE: u32 = 64;
 // Sha1Sum sha1 sum array
 pub type Sha1Sum = [u8; SHA1_SIZE as usize];
 
-// Sha0Context sha-0 context
+// Sha0Context streaming sha-0 state; finalize consumes it, so a finished
+// context can't be fed more data. Clone it to reuse a common prefix.
+#[derive(Clone)]
 pub struct Sha0Context {
     count: u64,
     buf: [u8; 64],
-    state: [u32; 8],
+    state: [u32; 5],
 }
 
-impl Sha0Context {
-    // Init sha0 init
-    pub fn init(&mut self) {
-        self.state[0] = 0x67452301;
-        self.state[1] = 0xEFCDAB89;
-        self.state[2] = 0x98BADCFE;
-        self.state[3] = 0x10325476;
-        self.state[4] = 0xC3D2E1F0;
-        self.count = 0;
+impl Default for Sha0Context {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
+impl Sha0Context {
+    // Init sha0 init
     pub fn new() -> Self {
-        let mut ctx = Self {
+        Self {
             count: 0,
             buf: [0; 64],
-            state: [0; 8],
-        };
-        ctx.init();
-        ctx
+            state: [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0],
+        }
     }
 }
 
@@ -43,26 +40,17 @@ fn rol(bits: i32, value: u32) -> u32 {
 }
 
 impl Sha0Context {
-    // Transform sha0 transform
-    pub fn transform(&mut self) {
+    // Transform sha0 transform of the block in buf
+    fn transform(&mut self) {
         let mut w = [0u32; 80];
 
-        let mut p = 0;
-        let mut t = 0;
-
-        while t < 16 {
-            let mut tmp = (self.buf[p + 0] as u32) << 24;
-            tmp |= (self.buf[p + 1] as u32) << 16;
-            tmp |= (self.buf[p + 2] as u32) << 8;
-            tmp |= (self.buf[p + 3] as u32) << 0;
-            w[t] = tmp;
-            p += 4;
-            t += 1;
+        for (t, word) in self.buf.chunks_exact(4).enumerate() {
+            w[t] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
         }
 
-        while t < 80 {
+        // Unlike SHA-1, the schedule is not rotated
+        for t in 16..80 {
             w[t] = w[t - 3] ^ w[t - 8] ^ w[t - 14] ^ w[t - 16];
-            t += 1;
         }
 
         let mut a = self.state[0];
@@ -71,9 +59,8 @@ impl Sha0Context {
         let mut d = self.state[3];
         let mut e = self.state[4];
 
-        t = 0;
-        while t < 80 {
-            let mut tmp = rol(5, a).wrapping_add(e).wrapping_add(w[t]);
+        for (t, &wt) in w.iter().enumerate() {
+            let mut tmp = rol(5, a).wrapping_add(e).wrapping_add(wt);
             if t < 20 {
                 tmp = tmp.wrapping_add(d ^ (b & (c ^ d))).wrapping_add(0x5A827999);
             } else if t < 40 {
@@ -89,7 +76,6 @@ impl Sha0Context {
             c = rol(30, b);
             b = a;
             a = tmp;
-            t += 1;
         }
 
         self.state[0] = self.state[0].wrapping_add(a);
@@ -99,16 +85,16 @@ impl Sha0Context {
         self.state[4] = self.state[4].wrapping_add(e);
     }
 
-    // Update sha0 update
-    pub fn update(&mut self, data: &[u8]) {
+    // Update sha0 update; data may be fed in pieces of any size
+    pub fn update(&mut self, mut data: &[u8]) {
         let mut i = (self.count & 63) as usize;
+        self.count = self.count.wrapping_add(data.len() as u64);
 
-        let l = data.len();
-        self.count = self.count.wrapping_add(l as u64);
-
-        for &d in data {
-            self.buf[i] = d;
-            i += 1;
+        while !data.is_empty() {
+            let n = data.len().min(64 - i);
+            self.buf[i..i + n].copy_from_slice(&data[..n]);
+            data = &data[n..];
+            i += n;
             if i == 64 {
                 self.transform();
                 i = 0;
@@ -117,28 +103,20 @@ impl Sha0Context {
     }
 
     // Final sha0 final
-    pub fn final_hash(&mut self) {
-        let cnt = self.count * 8;
+    pub fn finalize(mut self) -> Sha1Sum {
+        let cnt = self.count.wrapping_mul(8);
 
         self.update(&[0x80]);
         while self.count & 63 != 56 {
             self.update(&[0x0]);
         }
+        self.update(&cnt.to_be_bytes());
 
-        for i in 0..8 {
-            let tmp = (cnt >> ((7 - i) * 8)) as u8;
-            self.update(&[tmp]);
-        }
-
-        let mut p = 0;
-        for i in 0..5 {
-            let tmp = self.state[i];
-            self.buf[p + 0] = (tmp >> 24) as u8;
-            self.buf[p + 1] = (tmp >> 16) as u8;
-            self.buf[p + 2] = (tmp >> 8) as u8;
-            self.buf[p + 3] = (tmp >> 0) as u8;
-            p += 4;
+        let mut ret = [0u8; SHA1_SIZE as usize];
+        for (out, word) in ret.chunks_exact_mut(4).zip(self.state) {
+            out.copy_from_slice(&word.to_be_bytes());
         }
+        ret
     }
 }
 
@@ -146,8 +124,39 @@ impl Sha0Context {
 pub fn sha0(data: &[u8]) -> Sha1Sum {
     let mut ctx = Sha0Context::new();
     ctx.update(data);
-    ctx.final_hash();
-    let mut ret = [0u8; SHA1_SIZE as usize];
-    ret.copy_from_slice(&ctx.buf[0..SHA1_SIZE as usize]);
-    ret
+    ctx.finalize()
+}
+
+// HashPassword - sha-0 of the password followed by the upper-cased username,
+// as stored by the server and used for password authentication
+pub fn hash_password(username: &str, password: &str) -> Sha1Sum {
+    let mut ctx = Sha0Context::new();
+    ctx.update(password.as_bytes());
+    // StrUpper only folds ASCII letters
+    ctx.update(username.to_ascii_uppercase().as_bytes());
+    ctx.finalize()
+}
+
+// SecurePassword - sha-0 of the hashed password followed by the server random;
+// the value the client sends as "secure_password"
+pub fn secure_password(hashed_password: &Sha1Sum, random: &Sha1Sum) -> Sha1Sum {
+    let mut ctx = Sha0Context::new();
+    ctx.update(hashed_password);
+    ctx.update(random);
+    ctx.finalize()
+}
+
+// VerifySecurePassword - check a received "secure_password" against the stored
+// hash; the comparison takes the same time wherever the first mismatch is
+pub fn verify_secure_password(hashed_password: &Sha1Sum, random: &Sha1Sum, received: &[u8]) -> bool {
+    constant_time_eq(&secure_password(hashed_password, random), received)
+}
+
+// Compare without an early exit; only the length is allowed to leak
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(diff) == 0
 }
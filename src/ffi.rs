@@ -0,0 +1,359 @@
+// ffi.rs - C ABI for pack encoding and decoding (feature "ffi")
+//
+// The header is generated by cbindgen; include/mayaqua.h is the committed
+// copy (see build.rs for how to regenerate it).
+//
+// Ownership: a pack returned by mayaqua_pack_new or mayaqua_read_pack, and
+// any string or byte buffer returned through an out parameter, belongs to
+// the caller and must be released with the matching mayaqua_*_free function.
+// Pointer arguments are only borrowed for the duration of the call. Every
+// fallible function returns a MayaquaError and writes its out parameters
+// only on MAYAQUA_ERROR_OK.
+//
+// Doc comments here use /// so cbindgen copies them into the header.
+
+use crate::pack_reader::read_pack;
+use crate::pack_types::*;
+use crate::pack_writer::WriteOptions;
+use std::ffi::{c_char, CStr, CString};
+use std::io::Cursor;
+use std::ptr;
+use std::slice;
+
+/// Result code of every fallible mayaqua_* function
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MayaquaError {
+    Ok = 0,
+    /// A required pointer argument was NULL
+    NullPointer = 1,
+    /// A string is not valid UTF-8, or contains an interior NUL
+    InvalidString = 2,
+    /// No element with that name
+    NotFound = 3,
+    /// The element exists but holds another type
+    WrongType = 4,
+    NumberExceeds = 5,
+    SizeOver = 6,
+    InvalidType = 7,
+    SameNameExists = 8,
+    ZeroNumValue = 9,
+    InvalidName = 10,
+    /// The input ended early or could not be read
+    Io = 11,
+}
+
+impl From<PackErrorKind> for MayaquaError {
+    fn from(kind: PackErrorKind) -> Self {
+        match kind {
+            PackErrorKind::NumberExceeds => MayaquaError::NumberExceeds,
+            PackErrorKind::SizeOver => MayaquaError::SizeOver,
+            PackErrorKind::InvalidType => MayaquaError::InvalidType,
+            PackErrorKind::SameNameExists => MayaquaError::SameNameExists,
+            PackErrorKind::ZeroNumValue => MayaquaError::ZeroNumValue,
+            PackErrorKind::Utf8 => MayaquaError::InvalidString,
+            PackErrorKind::InvalidName => MayaquaError::InvalidName,
+            PackErrorKind::IoError => MayaquaError::Io,
+        }
+    }
+}
+
+impl From<PackError> for MayaquaError {
+    fn from(e: PackError) -> Self {
+        e.kind.into()
+    }
+}
+
+impl From<PackFieldError> for MayaquaError {
+    fn from(e: PackFieldError) -> Self {
+        match e.kind {
+            PackFieldErrorKind::Missing | PackFieldErrorKind::IndexOutOfRange { .. } => MayaquaError::NotFound,
+            PackFieldErrorKind::WrongType { .. } => MayaquaError::WrongType,
+        }
+    }
+}
+
+fn status(r: Result<(), MayaquaError>) -> MayaquaError {
+    r.err().unwrap_or(MayaquaError::Ok)
+}
+
+unsafe fn str_arg<'a>(s: *const c_char) -> Result<&'a str, MayaquaError> {
+    if s.is_null() {
+        return Err(MayaquaError::NullPointer);
+    }
+    CStr::from_ptr(s).to_str().map_err(|_| MayaquaError::InvalidString)
+}
+
+unsafe fn pack_arg<'a>(p: *const Pack) -> Result<&'a Pack, MayaquaError> {
+    p.as_ref().ok_or(MayaquaError::NullPointer)
+}
+
+unsafe fn out_arg<'a, T>(out: *mut T) -> Result<&'a mut T, MayaquaError> {
+    out.as_mut().ok_or(MayaquaError::NullPointer)
+}
+
+// Hand a byte buffer to C; released by mayaqua_bytes_free with the same length
+unsafe fn put_bytes(data: Vec<u8>, out_data: *mut *mut u8, out_len: *mut usize) -> Result<(), MayaquaError> {
+    let (out_data, out_len) = (out_arg(out_data)?, out_arg(out_len)?);
+    *out_len = data.len();
+    *out_data = Box::into_raw(data.into_boxed_slice()) as *mut u8;
+    Ok(())
+}
+
+unsafe fn put_string(s: &str, out: *mut *mut c_char) -> Result<(), MayaquaError> {
+    let out = out_arg(out)?;
+    *out = CString::new(s).map_err(|_| MayaquaError::InvalidString)?.into_raw();
+    Ok(())
+}
+
+unsafe fn add_value(p: *mut Pack, name: *const c_char, value: Value, is_bool: bool) -> MayaquaError {
+    status((|| {
+        let p = p.as_mut().ok_or(MayaquaError::NullPointer)?;
+        let mut e = Element::new(str_arg(name)?.to_string(), value.value_type());
+        e.values.push(value);
+        e.json_hint_is_bool = is_bool;
+        e.validate()?;
+        p.add_element(e)?;
+        Ok(())
+    })())
+}
+
+/// Create an empty pack. Release it with mayaqua_pack_free.
+#[no_mangle]
+pub extern "C" fn mayaqua_pack_new() -> *mut Pack {
+    Box::into_raw(Box::new(Pack::new()))
+}
+
+/// Release a pack. NULL is ignored.
+///
+/// # Safety
+/// `p` must be NULL or a pack from mayaqua_pack_new / mayaqua_read_pack that
+/// has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_free(p: *mut Pack) {
+    if !p.is_null() {
+        drop(Box::from_raw(p));
+    }
+}
+
+/// Number of elements in the pack, 0 for NULL.
+///
+/// # Safety
+/// `p` must be NULL or a valid pack.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_get_num(p: *const Pack) -> u32 {
+    p.as_ref().map_or(0, |p| p.get_num() as u32)
+}
+
+/// Add an Int element.
+///
+/// # Safety
+/// `p` must be a valid pack and `name` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_add_int(p: *mut Pack, name: *const c_char, value: u32) -> MayaquaError {
+    add_value(p, name, Value::Int(value), false)
+}
+
+/// Add an Int64 element.
+///
+/// # Safety
+/// `p` must be a valid pack and `name` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_add_int64(p: *mut Pack, name: *const c_char, value: u64) -> MayaquaError {
+    add_value(p, name, Value::Int64(value), false)
+}
+
+/// Add a bool, sent as an Int element of 0 or 1.
+///
+/// # Safety
+/// `p` must be a valid pack and `name` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_add_bool(p: *mut Pack, name: *const c_char, value: bool) -> MayaquaError {
+    add_value(p, name, Value::Int(value as u32), true)
+}
+
+/// Add a Str element; `value` must be UTF-8.
+///
+/// # Safety
+/// `p` must be a valid pack, `name` and `value` NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_add_str(p: *mut Pack, name: *const c_char, value: *const c_char) -> MayaquaError {
+    match str_arg(value) {
+        Ok(v) => add_value(p, name, Value::Str(v.to_string()), false),
+        Err(e) => e,
+    }
+}
+
+/// Add a UniStr element; `value` must be UTF-8.
+///
+/// # Safety
+/// `p` must be a valid pack, `name` and `value` NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_add_uni_str(p: *mut Pack, name: *const c_char, value: *const c_char) -> MayaquaError {
+    match str_arg(value) {
+        Ok(v) => add_value(p, name, Value::UniStr(v.to_string()), false),
+        Err(e) => e,
+    }
+}
+
+/// Add a Data element holding a copy of `len` bytes at `data`.
+///
+/// # Safety
+/// `p` must be a valid pack, `name` a NUL-terminated string and `data` valid
+/// for `len` bytes (it may be NULL when `len` is 0).
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_add_data(p: *mut Pack, name: *const c_char, data: *const u8, len: usize) -> MayaquaError {
+    let bytes = match (data.is_null(), len) {
+        (_, 0) => Vec::new(),
+        (true, _) => return MayaquaError::NullPointer,
+        (false, _) => slice::from_raw_parts(data, len).to_vec(),
+    };
+    add_value(p, name, Value::Data(bytes), false)
+}
+
+/// Read the first value of an Int element.
+///
+/// # Safety
+/// `p` must be a valid pack, `name` a NUL-terminated string and `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_get_int(p: *const Pack, name: *const c_char, out: *mut u32) -> MayaquaError {
+    status((|| {
+        *out_arg(out)? = pack_arg(p)?.try_get_int(str_arg(name)?)?;
+        Ok(())
+    })())
+}
+
+/// Read the first value of an Int64 element.
+///
+/// # Safety
+/// `p` must be a valid pack, `name` a NUL-terminated string and `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_get_int64(p: *const Pack, name: *const c_char, out: *mut u64) -> MayaquaError {
+    status((|| {
+        *out_arg(out)? = pack_arg(p)?.try_get_int64(str_arg(name)?)?;
+        Ok(())
+    })())
+}
+
+/// Read an Int element as a bool (non-zero is true).
+///
+/// # Safety
+/// `p` must be a valid pack, `name` a NUL-terminated string and `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_get_bool(p: *const Pack, name: *const c_char, out: *mut bool) -> MayaquaError {
+    status((|| {
+        *out_arg(out)? = pack_arg(p)?.try_get_bool(str_arg(name)?)?;
+        Ok(())
+    })())
+}
+
+/// Copy a Str element into a new string. Release it with mayaqua_string_free.
+///
+/// # Safety
+/// `p` must be a valid pack, `name` a NUL-terminated string and `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_get_str(p: *const Pack, name: *const c_char, out: *mut *mut c_char) -> MayaquaError {
+    status((|| put_string(pack_arg(p)?.try_get_str(str_arg(name)?)?, out))())
+}
+
+/// Copy a UniStr element into a new UTF-8 string. Release it with
+/// mayaqua_string_free.
+///
+/// # Safety
+/// `p` must be a valid pack, `name` a NUL-terminated string and `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_get_uni_str(p: *const Pack, name: *const c_char, out: *mut *mut c_char) -> MayaquaError {
+    status((|| put_string(pack_arg(p)?.try_get_uni_str(str_arg(name)?)?, out))())
+}
+
+/// Copy a Data element into a new buffer. Release it with mayaqua_bytes_free.
+///
+/// # Safety
+/// `p` must be a valid pack, `name` a NUL-terminated string, `out_data` and
+/// `out_len` writable.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_get_data(
+    p: *const Pack,
+    name: *const c_char,
+    out_data: *mut *mut u8,
+    out_len: *mut usize,
+) -> MayaquaError {
+    status((|| put_bytes(pack_arg(p)?.try_get_data(str_arg(name)?)?.to_vec(), out_data, out_len))())
+}
+
+/// Serialize the pack, rejecting anything a SoftEther peer would refuse.
+/// Release the buffer with mayaqua_bytes_free.
+///
+/// # Safety
+/// `p` must be a valid pack, `out_data` and `out_len` writable.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_pack_to_buf(p: *const Pack, out_data: *mut *mut u8, out_len: *mut usize) -> MayaquaError {
+    status((|| {
+        let opts = WriteOptions { strict: true, canonical: false };
+        put_bytes(pack_arg(p)?.to_buf_with(&opts)?, out_data, out_len)
+    })())
+}
+
+/// Parse a serialized pack. Release it with mayaqua_pack_free.
+///
+/// # Safety
+/// `data` must be valid for `len` bytes and `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_read_pack(data: *const u8, len: usize, out: *mut *mut Pack) -> MayaquaError {
+    status((|| {
+        let out = out_arg(out)?;
+        if data.is_null() {
+            return Err(MayaquaError::NullPointer);
+        }
+        let p = read_pack(&mut Cursor::new(slice::from_raw_parts(data, len)))?;
+        *out = Box::into_raw(Box::new(p));
+        Ok(())
+    })())
+}
+
+/// Release a string returned by this library. NULL is ignored.
+///
+/// # Safety
+/// `s` must be NULL or a string from mayaqua_pack_get_str / _get_uni_str.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Release a buffer returned by this library. NULL is ignored.
+///
+/// # Safety
+/// `data` must be NULL or a buffer from mayaqua_pack_get_data /
+/// mayaqua_pack_to_buf, and `len` the length returned with it.
+#[no_mangle]
+pub unsafe extern "C" fn mayaqua_bytes_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
+
+/// A static description of an error code; never freed. Codes that are not
+/// a MayaquaError give "Unknown error".
+#[no_mangle]
+pub extern "C" fn mayaqua_error_string(code: u32) -> *const c_char {
+    // Matched on the number: a C caller can pass any value, and an
+    // out-of-range MayaquaError would be undefined behaviour
+    let s: &'static CStr = match code {
+        0 => c"OK",
+        1 => c"NULL pointer argument",
+        2 => c"Invalid string",
+        3 => c"Element not found",
+        4 => c"Element has another type",
+        5 => c"Number exceeds",
+        6 => c"Size over",
+        7 => c"Invalid type",
+        8 => c"Same name exists",
+        9 => c"Zero num value",
+        10 => c"Invalid element name",
+        11 => c"IO error",
+        _ => c"Unknown error",
+    };
+    s.as_ptr()
+}
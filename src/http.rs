@@ -1,7 +1,10 @@
 // http.rs - HTTP client functionality for SoftEther protocol
 
-use std::io;
-use crate::{Pack, AsyncSock};
+use std::io::{self, Cursor};
+use crate::{read_pack, Element, Pack, PackError, PackStream, AsyncSock, Value, ValueType};
+use rand::{Rng, RngCore};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tracing::{debug, debug_span, trace, warn, Instrument};
 
 // HTTP constants - exactly matching Go version
 pub const HTTP_CONTENT_TYPE: &str = "application/octet-stream";
@@ -11,15 +14,97 @@ pub const HTTP_VPN_TARGET: &str = "/vpnsvc/vpn.cgi";
 pub const HTTP_VPN_TARGET2: &str = "/vpnsvc/connect.cgi";
 pub const HTTP_PACK_RAND_SIZE_MAX: u32 = 1000;
 
+// Name of the random padding element added to every pack sent over HTTP
+pub const HTTP_PACK_PADDING_NAME: &str = "pencore";
+
+// HttpSendOptions - how http_client_send_stream sends a pack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HttpSendOptions {
+    // Add random "pencore" padding so request sizes can't be fingerprinted
+    pub pencore: bool,
+}
+
+impl Default for HttpSendOptions {
+    fn default() -> Self {
+        Self { pencore: true }
+    }
+}
+
+// CreateDummyValue - a "pencore" element of Rand32() % HTTP_PACK_RAND_SIZE_MAX random bytes
+pub fn create_dummy_value() -> Element {
+    let mut rng = rand::thread_rng();
+    let mut data = vec![0u8; rng.gen_range(0..HTTP_PACK_RAND_SIZE_MAX) as usize];
+    rng.fill_bytes(&mut data);
+
+    let mut e = Element::new(HTTP_PACK_PADDING_NAME.to_string(), ValueType::Data);
+    e.values.push(Value::Data(data));
+    e
+}
+
+// ReadHttpPack parse a pack received over HTTP, dropping the padding
+pub fn read_http_pack(body: &[u8]) -> Result<Pack, PackError> {
+    let mut p = read_pack(&mut Cursor::new(body))?;
+    p.remove(HTTP_PACK_PADDING_NAME);
+    Ok(p)
+}
+
 // HttpClientSend sends a Pack via HTTP POST - exact same as Go
 pub async fn http_client_send(sock: &mut AsyncSock, pack: &Pack) -> io::Result<Vec<u8>> {
-    // Serialize the pack
-    let pack_data = pack.to_buf().map_err(|e| {
-        io::Error::new(io::ErrorKind::InvalidData, format!("Pack serialization failed: {}", e))
-    })?;
+    trace!(pack = %pack.redacted(), "http_client_send");
+    let stream = PackStream::<tokio::io::Empty>::new(pack);
+    http_client_send_stream(sock, stream, &HttpSendOptions::default()).await
+}
+
+// HttpClientCall sends a pack and parses the reply, without its padding
+pub async fn http_client_call(sock: &mut AsyncSock, pack: &Pack) -> io::Result<Pack> {
+    let body = http_client_send(sock, pack).await?;
+    read_http_pack(&body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+// HttpClientSendStream sends a PackStream via HTTP POST; the body is streamed
+// straight to the socket, so Data elements are never copied into one buffer
+pub async fn http_client_send_stream<R: AsyncRead + Unpin>(
+    sock: &mut AsyncSock,
+    mut stream: PackStream<'_, R>,
+    opts: &HttpSendOptions,
+) -> io::Result<Vec<u8>> {
+    // A pack that already carries padding is sent as is, like PackAddData would
+    if opts.pencore {
+        let _ = stream.add_element(create_dummy_value());
+    }
+
+    let span = debug_span!("http_exchange", target = HTTP_VPN_TARGET, host = %sock.remote_ip);
+    http_client_send_inner(sock, stream).instrument(span).await
+}
+
+async fn http_client_send_inner<R: AsyncRead + Unpin>(
+    sock: &mut AsyncSock,
+    stream: PackStream<'_, R>,
+) -> io::Result<Vec<u8>> {
+    let host = sock.remote_ip.clone();
+    write_http_request(&mut sock.tls_stream, &host, stream).await?;
     
+    // Read HTTP response
+    read_http_response(sock).await
+}
+
+// WriteHttpRequest write the POST request carrying a PackStream to w
+//
+// Content-Length is taken from the stream up front. If the body then fails
+// part way, typically because a reader errors or ends early, the request on
+// the wire is truncated and the server would wait for the rest, so w is shut
+// down before the error is returned and must not be used again.
+pub async fn write_http_request<W: AsyncWrite + Unpin, R: AsyncRead + Unpin>(
+    w: &mut W,
+    host: &str,
+    stream: PackStream<'_, R>,
+) -> io::Result<()> {
+    let invalid = |e: PackError| io::Error::new(io::ErrorKind::InvalidData, format!("Pack serialization failed: {}", e));
+    stream.check_value_types().map_err(invalid)?;
+
     // Create HTTP POST request
-    let content_length = pack_data.len();
+    let content_length = stream.encoded_len();
+    trace!(elements = stream.get_num(), content_length, "sending pack");
     
     let request = format!(
         "POST {} HTTP/1.1\r\n\
@@ -28,19 +113,21 @@ pub async fn http_client_send(sock: &mut AsyncSock, pack: &Pack) -> io::Result<V
          Content-Type: application/octet-stream\r\n\
          \r\n",
         HTTP_VPN_TARGET,
-        sock.remote_ip,
+        host,
         content_length
     );
     
-    // Send HTTP headers
-    sock.write_all(request.as_bytes()).await?;
-    
-    // Send pack data
-    sock.write_all(&pack_data).await?;
-    sock.flush().await?;
-    
-    // Read HTTP response
-    read_http_response(sock).await
+    let sent = async {
+        // Send HTTP headers, then the pack data
+        w.write_all(request.as_bytes()).await?;
+        stream.write_to_async(w).await.map_err(invalid)?;
+        w.flush().await
+    }
+    .await;
+    if sent.is_err() {
+        let _ = w.shutdown().await;
+    }
+    sent
 }
 
 // Read HTTP response and extract body
@@ -53,7 +140,7 @@ async fn read_http_response(sock: &mut AsyncSock) -> io::Result<Vec<u8>> {
     
     // Read status line
     reader.read_line(&mut response_line).await?;
-    println!("[DEBUG] HTTP status line: {}", response_line.trim());
+    debug!(status = %response_line.trim(), "HTTP response");
     if !response_line.starts_with("HTTP/1.1 200") && !response_line.starts_with("HTTP/1.0 200") {
         return Err(io::Error::new(
             io::ErrorKind::InvalidData,
@@ -77,7 +164,7 @@ async fn read_http_response(sock: &mut AsyncSock) -> io::Result<Vec<u8>> {
             }
         }
         if header_line.to_lowercase().starts_with("content-type:") {
-            println!("[DEBUG] HTTP Content-Type: {}", header_line.trim());
+            debug!(header = %header_line.trim(), "HTTP header");
         }
     }
     
@@ -86,11 +173,10 @@ async fn read_http_response(sock: &mut AsyncSock) -> io::Result<Vec<u8>> {
         let mut body = vec![0u8; content_length];
         use tokio::io::AsyncReadExt;
         reader.read_exact(&mut body).await?;
-        println!("[DEBUG] HTTP response body length: {} bytes", body.len());
-        // If it looks like HTML, show the first part
+        debug!(len = body.len(), "HTTP response body");
+        // An HTML page means this is not a VPN server; its content is not logged
         if body.len() > 10 && body.starts_with(b"<!DOCTYPE") {
-            let html_preview = String::from_utf8_lossy(&body[..std::cmp::min(200, body.len())]);
-            println!("[DEBUG] HTML response detected: {}", html_preview);
+            warn!(len = body.len(), "HTML response instead of a pack");
         }
         Ok(body)
     } else {
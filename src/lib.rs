@@ -3,20 +3,39 @@
 pub mod pack_types;
 pub mod pack_reader;
 pub mod pack_writer;
+pub mod pack_json;
+pub mod pack_serde;
+pub mod pack_ref;
+pub mod pack_async;
+pub mod pack_stream;
+pub mod pack_log;
 pub mod encrypt;
 pub mod memory;
 pub mod network;
 pub mod sock;
 pub mod http;
+// Private: a public `mayaqua` module would clash with the crate name in `use mayaqua::*`
+mod mayaqua;
+pub mod protocol;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 
 // Re-export commonly used types and functions
 pub use pack_types::*;
-pub use pack_reader::{read_pack, read_element, read_value};
+pub use pack_reader::{read_pack, read_pack_with_limits, read_element, read_value};
+pub use pack_json::{pack_to_json, json_to_pack};
+pub use pack_serde::{to_pack, from_pack, PackSerdeError};
+pub use pack_ref::{PackRef, ElementRef, ValueRef};
+pub use pack_async::{read_pack_async, read_pack_async_with_limits, write_pack_async, PackCodec};
+pub use pack_log::{is_secret_element_name, RedactedPack};
+pub use pack_stream::PackStream;
 #[allow(unused_imports)]
 pub use pack_writer::*;
 pub use encrypt::*;
 pub use sock::AsyncSock;
-pub use http::{http_client_send, HTTP_VPN_TARGET, HTTP_VPN_TARGET2, HTTP_PACK_RAND_SIZE_MAX};
+pub use mayaqua::{ErrServerIsNotVpn, ERR_SERVER_IS_NOT_VPN};
+pub use protocol::{ProtocolError, ServerHello, ClientAuth, AuthData, Welcome, UdpAccel, ErrorReply, AdditionalConnect};
+pub use http::{http_client_send, http_client_send_stream, http_client_call, write_http_request, read_http_pack, HttpSendOptions, HTTP_VPN_TARGET, HTTP_VPN_TARGET2, HTTP_PACK_RAND_SIZE_MAX};
 
 // Helper functions for socket operations
 pub async fn sock_send_all(sock: &mut AsyncSock, data: &[u8]) -> Result<(), anyhow::Error> {
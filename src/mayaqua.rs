@@ -2,7 +2,7 @@
 
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
 #[error("ERR_SERVER_IS_NOT_VPN")]
 pub struct ErrServerIsNotVpn;
 
@@ -23,7 +23,10 @@ pub fn read_buf_str<R: Read>(r: &mut R) -> io::Result<String> {
 // WriteBufStr write string to buffer
 pub fn write_buf_str<W: Write>(w: &mut W, s: &str) -> io::Result<()> {
     let b = s.as_bytes();
-    let num = (b.len() as u32) + 1;
+    let num = u32::try_from(b.len())
+        .ok()
+        .and_then(|n| n.checked_add(1))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "String too long"))?;
     w.write_all(&num.to_be_bytes())?;
     w.write_all(b)?;
     Ok(())
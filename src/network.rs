@@ -5,6 +5,7 @@ use webpki_roots;
 use std::io;
 use std::sync::Arc;
 use crate::sock::AsyncSock;
+use tracing::{debug, debug_span, Instrument};
 
 pub async fn tcp_connect(hostname: &str, port: u16) -> io::Result<AsyncSock> {
     tcp_connect_with_config(hostname, port, false).await
@@ -32,17 +33,26 @@ pub async fn tcp_connect_with_config(hostname: &str, port: u16, insecure_skip_ve
     let connector = TlsConnector::from(config);
     
     // Connect TCP first
-    let tcp_stream = TcpStream::connect(format!("{}:{}", hostname, port)).await?;
+    let tcp_stream = TcpStream::connect(format!("{}:{}", hostname, port))
+        .instrument(debug_span!("connect", host = %hostname, port))
+        .await?;
     
     // We can't clone tokio TcpStream, so we'll connect again for raw access
-    let raw_stream = TcpStream::connect(format!("{}:{}", hostname, port)).await?;
+    let raw_stream = TcpStream::connect(format!("{}:{}", hostname, port))
+        .instrument(debug_span!("connect_raw", host = %hostname, port))
+        .await?;
     
     // Create TLS connection
     let domain = rustls::pki_types::ServerName::try_from(hostname.to_string())
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
     
-    let tls_stream = connector.connect(domain, tcp_stream).await
-        .map_err(|e| io::Error::new(io::ErrorKind::ConnectionRefused, e))?;
+    let tls_stream = connector.connect(domain, tcp_stream)
+        .instrument(debug_span!("tls_handshake", server_name = %hostname, insecure_skip_verify))
+        .await
+        .map_err(|e| {
+            debug!(error = %e, "TLS handshake failed");
+            io::Error::new(io::ErrorKind::ConnectionRefused, e)
+        })?;
     
     let mut sock = AsyncSock::new(tls_stream, raw_stream)?;
     sock.insecure_skip_verify = insecure_skip_verify;
@@ -0,0 +1,185 @@
+// pack_async.rs - Async pack framing (SoftEther SendPack / RecvPack)
+//
+// On the wire a pack is sent as a big-endian u32 body size followed by the
+// serialized pack, once the HTTP phase of the connection is over.
+
+use crate::pack_reader::read_pack_with_limits;
+use crate::pack_types::*;
+use crate::pack_writer::{check_value_types, len_u32, write_element_header};
+use bytes::{Buf, BufMut, BytesMut};
+use std::io::Cursor;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_util::codec::{Decoder, Encoder};
+
+// Body buffers start at most this large and grow as data arrives
+const INITIAL_BODY_CAPACITY: usize = 64 * 1024;
+
+// Small values are batched up to this size before being written
+const WRITE_BUF_SIZE: usize = 64 * 1024;
+
+// Data values at least this large are written straight from the element
+const DIRECT_WRITE_MIN: usize = 4 * 1024;
+
+// RecvPack read a size-prefixed pack
+pub async fn read_pack_async<R: AsyncRead + Unpin>(r: &mut R) -> Result<Pack, PackError> {
+    read_pack_async_with_limits(r, &PackLimits::default()).await
+}
+
+// RecvPack read a size-prefixed pack, enforcing limits
+pub async fn read_pack_async_with_limits<R: AsyncRead + Unpin>(
+    r: &mut R,
+    limits: &PackLimits,
+) -> Result<Pack, PackError> {
+    let mut size_bytes = [0u8; 4];
+    r.read_exact(&mut size_bytes).await?;
+    let size = u32::from_be_bytes(size_bytes);
+
+    if size == 0 || size > MAX_PACK_SIZE || size as u64 > limits.max_total_size {
+        return Err(PackError::new(PackErrorKind::SizeOver).at(0));
+    }
+
+    let mut body = Vec::with_capacity((size as usize).min(INITIAL_BODY_CAPACITY));
+    r.take(size as u64).read_to_end(&mut body).await?;
+    if body.len() != size as usize {
+        let eof = std::io::Error::from(std::io::ErrorKind::UnexpectedEof);
+        return Err(PackError::from(eof).at(body.len() as u64));
+    }
+    read_pack_with_limits(&mut Cursor::new(body), limits)
+}
+
+// SendPack write a size-prefixed pack
+//
+// A pack that can't be serialized is rejected before the size goes out. If
+// writing fails after that, the peer has been promised more bytes than it
+// will get and every later RecvPack would be out of step, so w is shut down
+// before the error is returned and must not be used again.
+pub async fn write_pack_async<W: AsyncWrite + Unpin>(w: &mut W, p: &Pack) -> Result<(), PackError> {
+    let size = p.encoded_len();
+    if size > MAX_PACK_SIZE as u64 {
+        return Err(PackError::new(PackErrorKind::SizeOver).at(0));
+    }
+    check_value_types(p.iter())?;
+
+    if let Err(e) = write_frame_async(w, size as u32, p).await {
+        let _ = w.shutdown().await;
+        return Err(e);
+    }
+    Ok(())
+}
+
+async fn write_frame_async<W: AsyncWrite + Unpin>(w: &mut W, size: u32, p: &Pack) -> Result<(), PackError> {
+    w.write_all(&size.to_be_bytes()).await?;
+    p.write_to_async(w).await?;
+    w.flush().await?;
+    Ok(())
+}
+
+impl Pack {
+    // WriteToAsync stream the pack into w; large Data values are not copied
+    pub async fn write_to_async<W: AsyncWrite + Unpin>(&self, w: &mut W) -> Result<(), PackError> {
+        let mut buf = Vec::with_capacity(WRITE_BUF_SIZE);
+        buf.extend_from_slice(&len_u32(self.get_num())?.to_be_bytes());
+        write_elements_async(w, &mut buf, self.elements()).await?;
+        w.write_all(&buf).await?;
+        Ok(())
+    }
+}
+
+// Write elements through buf, which the caller must write out at the end
+pub(crate) async fn write_elements_async<W: AsyncWrite + Unpin>(
+    w: &mut W,
+    buf: &mut Vec<u8>,
+    elements: &[Element],
+) -> Result<(), PackError> {
+    for e in elements {
+        write_element_async(w, buf, e).await.map_err(|err| err.in_element(&e.name))?;
+    }
+    Ok(())
+}
+
+async fn write_element_async<W: AsyncWrite + Unpin>(
+    w: &mut W,
+    buf: &mut Vec<u8>,
+    e: &Element,
+) -> Result<(), PackError> {
+    write_element_header(buf, &e.name, e.type_, e.num_value())?;
+    for v in &e.values {
+        match v {
+            Value::Data(d) if d.len() >= DIRECT_WRITE_MIN && e.type_ == ValueType::Data => {
+                buf.extend_from_slice(&len_u32(d.len())?.to_be_bytes());
+                w.write_all(buf).await?;
+                buf.clear();
+                w.write_all(d).await?;
+            }
+            _ => v.write(buf, e.type_)?,
+        }
+        if buf.len() >= WRITE_BUF_SIZE {
+            w.write_all(buf).await?;
+            buf.clear();
+        }
+    }
+    Ok(())
+}
+
+// PackCodec - tokio_util codec for size-prefixed packs
+#[derive(Debug, Clone, Default)]
+pub struct PackCodec {
+    limits: PackLimits,
+}
+
+impl PackCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limits(limits: PackLimits) -> Self {
+        Self { limits }
+    }
+}
+
+impl Decoder for PackCodec {
+    type Item = Pack;
+    type Error = PackError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Pack>, PackError> {
+        if src.len() < 4 {
+            return Ok(None);
+        }
+        let size = u32::from_be_bytes([src[0], src[1], src[2], src[3]]);
+
+        if size == 0 || size > MAX_PACK_SIZE || size as u64 > self.limits.max_total_size {
+            return Err(PackError::new(PackErrorKind::SizeOver).at(0));
+        }
+        if src.len() < 4 + size as usize {
+            return Ok(None);
+        }
+
+        src.advance(4);
+        let body = src.split_to(size as usize);
+        read_pack_with_limits(&mut Cursor::new(&body[..]), &self.limits).map(Some)
+    }
+}
+
+impl Encoder<&Pack> for PackCodec {
+    type Error = PackError;
+
+    fn encode(&mut self, p: &Pack, dst: &mut BytesMut) -> Result<(), PackError> {
+        let body = p.to_buf()?;
+        if body.len() > MAX_PACK_SIZE as usize {
+            return Err(PackError::new(PackErrorKind::SizeOver).at(0));
+        }
+
+        dst.reserve(4 + body.len());
+        dst.put_u32(body.len() as u32);
+        dst.put_slice(&body);
+        Ok(())
+    }
+}
+
+impl Encoder<Pack> for PackCodec {
+    type Error = PackError;
+
+    fn encode(&mut self, p: Pack, dst: &mut BytesMut) -> Result<(), PackError> {
+        self.encode(&p, dst)
+    }
+}
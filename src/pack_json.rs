@@ -0,0 +1,343 @@
+// pack_json.rs - Pack <-> JSON conversion (SoftEther PackToJson / JsonToPack)
+//
+// Every JSON key carries a type suffix so the conversion is lossless:
+// _u32, _u64, _bool, _str, _utf, _bin (base64), _dt (ISO-8601) and _ip.
+// Array elements sharing a json_hint_group_name become an array of row objects.
+
+use crate::pack_types::*;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{Map, Value as JsonValue};
+use std::net::IpAddr;
+
+const IPV6_SUFFIXES: [&str; 3] = ["@ipv6_bool", "@ipv6_array", "@ipv6_scope_id"];
+
+// PackToJson convert a pack into a JSON object
+pub fn pack_to_json(p: &Pack) -> JsonValue {
+    let mut o = Map::new();
+
+    // Collect the array groups in order of first appearance
+    let mut groups: Vec<&str> = Vec::new();
+    for e in &p.elements {
+        if is_array_element(e)
+            && !e.json_hint_group_name.is_empty()
+            && !groups.iter().any(|g| g.eq_ignore_ascii_case(&e.json_hint_group_name))
+        {
+            groups.push(&e.json_hint_group_name);
+        }
+    }
+    // Groups that were declared but never filled still show up, as empty arrays
+    for name in p.json_subitem_names() {
+        if !groups.iter().any(|g| g.eq_ignore_ascii_case(name)) {
+            groups.push(name);
+        }
+    }
+
+    for group in groups {
+        let members: Vec<&Element> = p
+            .elements
+            .iter()
+            .filter(|e| is_array_element(e) && e.json_hint_group_name.eq_ignore_ascii_case(group))
+            .collect();
+
+        // Parallel arrays of different lengths can't be zipped into rows
+        let count = members.iter().map(|e| e.num_value()).find(|&n| n != 0).unwrap_or(0);
+        if members.iter().any(|e| e.num_value() != count && e.num_value() != 0) {
+            continue;
+        }
+
+        let mut rows = vec![Map::new(); count];
+        for e in members {
+            for (i, row) in rows.iter_mut().enumerate().take(e.num_value()) {
+                element_to_json(row, p, e, i as u32);
+            }
+        }
+        o.insert(group.to_string(), JsonValue::Array(rows.into_iter().map(JsonValue::Object).collect()));
+    }
+
+    for e in &p.elements {
+        if !is_array_element(e) {
+            element_to_json(&mut o, p, e, 0);
+        } else if e.json_hint_group_name.is_empty() {
+            if let Some(suffix) = json_suffix(p, e) {
+                let values = (0..e.num_value() as u32)
+                    .map(|i| value_to_json(p, e, i).unwrap_or(JsonValue::Null))
+                    .collect();
+                o.insert(format!("{}{}", e.name, suffix), JsonValue::Array(values));
+            }
+        }
+    }
+
+    JsonValue::Object(o)
+}
+
+// JsonToPack convert a JSON object into a pack
+pub fn json_to_pack(v: &JsonValue) -> Result<Pack, PackError> {
+    let o = v.as_object().ok_or_else(invalid_type)?;
+    let mut p = Pack::new();
+
+    for (name, value) in o {
+        if let JsonValue::Array(a) = value {
+            // Arrays of row objects (and empty ones without a type suffix) are groups
+            let is_group = a.first().map_or(split_json_suffix(name).is_none(), JsonValue::is_object);
+            if is_group {
+                p.set_current_json_group_name(name);
+            }
+            let total = a.len() as u32;
+            for (i, item) in a.iter().enumerate() {
+                if let JsonValue::Object(row) = item {
+                    for (k, v) in row {
+                        add_json_value(&mut p, v, k, i as u32, total, Some(name))?;
+                    }
+                } else {
+                    add_json_value(&mut p, item, name, i as u32, total, Some(""))?;
+                }
+            }
+            p.set_current_json_group_name("");
+        } else {
+            add_json_value(&mut p, value, name, 0, 1, None)?;
+        }
+    }
+
+    Ok(p)
+}
+
+fn is_array_element(e: &Element) -> bool {
+    e.num_value() >= 2 || e.json_hint_is_array
+}
+
+// An element belongs to an IP triplet if it carries the hint or, for packs
+// read off the wire (which carry no hints), if its companions are present
+fn is_ip_element(p: &Pack, e: &Element) -> bool {
+    if e.json_hint_is_ip {
+        return true;
+    }
+    if let Some(base) = IPV6_SUFFIXES.iter().find_map(|s| e.name.strip_suffix(s)) {
+        return p.get_element(base, Some(ValueType::Int)).is_some();
+    }
+    e.type_ == ValueType::Int
+        && p.get_element(&format!("{}@ipv6_bool", e.name), Some(ValueType::Int)).is_some()
+}
+
+// DetermineJsonSuffixForPackElement
+fn json_suffix(p: &Pack, e: &Element) -> Option<&'static str> {
+    let is_ip = is_ip_element(p, e);
+    match e.type_ {
+        ValueType::Int if is_ip => (!e.name.contains('@')).then_some("_ip"),
+        ValueType::Int if e.json_hint_is_bool => Some("_bool"),
+        ValueType::Int => Some("_u32"),
+        _ if is_ip => None,
+        ValueType::Int64 if e.json_hint_is_date_time => Some("_dt"),
+        ValueType::Int64 => Some("_u64"),
+        ValueType::Data => Some("_bin"),
+        ValueType::Str => Some("_str"),
+        ValueType::UniStr => Some("_utf"),
+    }
+}
+
+// PackElementToJsonObject
+fn element_to_json(o: &mut Map<String, JsonValue>, p: &Pack, e: &Element, index: u32) {
+    if let (Some(suffix), Some(v)) = (json_suffix(p, e), value_to_json(p, e, index)) {
+        o.insert(format!("{}{}", e.name, suffix), v);
+    }
+}
+
+fn value_to_json(p: &Pack, e: &Element, index: u32) -> Option<JsonValue> {
+    let v = match (json_suffix(p, e)?, e.values.get(index as usize)?) {
+        ("_ip", _) => JsonValue::String(ip_to_str(p.get_ip_addr_with_scope_ex(&e.name, index)?)),
+        ("_bool", Value::Int(i)) => JsonValue::Bool(*i != 0),
+        ("_dt", Value::Int64(t)) => JsonValue::String(time64_to_json_str(*t)),
+        (_, Value::Int(i)) => JsonValue::from(*i),
+        (_, Value::Int64(i)) => JsonValue::from(*i),
+        (_, Value::Str(s)) | (_, Value::UniStr(s)) => JsonValue::String(s.clone()),
+        (_, Value::Data(d)) => JsonValue::String(BASE64.encode(d)),
+    };
+    Some(v)
+}
+
+// JsonTryParseValueAddToPack - fields with an unknown suffix or a null value are skipped
+fn add_json_value(
+    p: &mut Pack,
+    v: &JsonValue,
+    key: &str,
+    index: u32,
+    total: u32,
+    group: Option<&str>,
+) -> Result<(), PackError> {
+    if v.is_null() {
+        return Ok(());
+    }
+    let Some((name, suffix)) = split_json_suffix(key) else {
+        return Ok(());
+    };
+    add_json_value_as(p, v, name, suffix, index, total, group).map_err(|e| e.in_element(name))
+}
+
+fn add_json_value_as(
+    p: &mut Pack,
+    v: &JsonValue,
+    name: &str,
+    suffix: &str,
+    index: u32,
+    total: u32,
+    group: Option<&str>,
+) -> Result<(), PackError> {
+    let value = match suffix {
+        "_bool" => Value::Int((json_to_u64(v)? != 0) as u32),
+        "_u32" => Value::Int(u32::try_from(json_to_u64(v)?).map_err(|_| invalid_type())?),
+        "_u64" => Value::Int64(json_to_u64(v)?),
+        "_str" => Value::Str(json_to_string(v)?),
+        "_utf" => Value::UniStr(json_to_string(v)?),
+        "_bin" => {
+            let s = v.as_str().ok_or_else(invalid_type)?;
+            Value::Data(BASE64.decode(s).map_err(|_| invalid_type())?)
+        }
+        "_dt" => match v {
+            JsonValue::String(s) => Value::Int64(json_str_to_time64(s).ok_or_else(invalid_type)?),
+            _ => Value::Int64(json_to_u64(v)?),
+        },
+        "_ip" => {
+            let s = v.as_str().ok_or_else(invalid_type)?;
+            let (ip, scope_id) = str_to_ip(s).ok_or_else(invalid_type)?;
+            return p.add_ip_value_ex(name, ip, scope_id, index, total, group).map(|_| ());
+        }
+        _ => return Ok(()),
+    };
+
+    let e = p.add_value_ex(name, value, index, total, group)?;
+    e.json_hint_is_bool = suffix == "_bool";
+    e.json_hint_is_date_time = suffix == "_dt";
+    Ok(())
+}
+
+fn invalid_type() -> PackError {
+    PackErrorKind::InvalidType.into()
+}
+
+fn split_json_suffix(key: &str) -> Option<(&str, &'static str)> {
+    const SUFFIXES: [&str; 8] = ["_bool", "_u32", "_u64", "_str", "_utf", "_bin", "_dt", "_ip"];
+    SUFFIXES.iter().find_map(|&s| {
+        let at = key.len().checked_sub(s.len())?;
+        (at > 0 && key.is_char_boundary(at) && key[at..].eq_ignore_ascii_case(s)).then(|| (&key[..at], s))
+    })
+}
+
+// IPToStr - a non-zero IPv6 scope id is appended as "%id"
+fn ip_to_str((ip, scope_id): (IpAddr, u32)) -> String {
+    match ip {
+        IpAddr::V6(_) if scope_id != 0 => format!("{}%{}", ip, scope_id),
+        _ => ip.to_string(),
+    }
+}
+
+fn str_to_ip(s: &str) -> Option<(IpAddr, u32)> {
+    match s.trim().split_once('%') {
+        Some((addr, scope)) => Some((IpAddr::V6(addr.parse().ok()?), scope.parse().ok()?)),
+        None => Some((s.trim().parse().ok()?, 0)),
+    }
+}
+
+// Negative, fractional and out-of-range numbers are rejected, not wrapped or
+// truncated; a float is accepted only if it is a whole number such as 5.0
+fn json_to_u64(v: &JsonValue) -> Result<u64, PackError> {
+    match v {
+        JsonValue::Bool(b) => Ok(*b as u64),
+        JsonValue::Number(n) => n
+            .as_u64()
+            .or_else(|| {
+                let f = n.as_f64()?;
+                (f >= 0.0 && f.fract() == 0.0 && f < u64::MAX as f64).then_some(f as u64)
+            })
+            .ok_or_else(invalid_type),
+        JsonValue::String(s) => {
+            let s = s.trim();
+            if s.eq_ignore_ascii_case("true") || s.eq_ignore_ascii_case("yes") {
+                Ok(1)
+            } else if s.eq_ignore_ascii_case("false") || s.eq_ignore_ascii_case("no") || s.is_empty() {
+                Ok(0)
+            } else {
+                s.parse().map_err(|_| invalid_type())
+            }
+        }
+        _ => Err(invalid_type()),
+    }
+}
+
+fn json_to_string(v: &JsonValue) -> Result<String, PackError> {
+    match v {
+        JsonValue::String(s) => Ok(s.clone()),
+        JsonValue::Number(n) => Ok(n.to_string()),
+        JsonValue::Bool(b) => Ok(b.to_string()),
+        _ => Err(invalid_type()),
+    }
+}
+
+// SystemTime64ToJsonStr - milliseconds since the Unix epoch to "YYYY-MM-DDTHH:MM:SS.mmmZ"
+pub fn time64_to_json_str(t: u64) -> String {
+    let ms = t % 1000;
+    let secs = t / 1000;
+    let (days, rem) = (secs / 86400, secs % 86400);
+    let (y, m, d) = civil_from_days(days as i64);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        y,
+        m,
+        d,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        ms
+    )
+}
+
+// JsonStrToSystemTime64 - accepts "YYYY-MM-DDTHH:MM:SS[.fff][Z]"
+pub fn json_str_to_time64(s: &str) -> Option<u64> {
+    let s = s.trim().trim_end_matches(['Z', 'z']);
+    let (date, time) = s.split_once(['T', 't', ' ']).unwrap_or((s, "00:00:00"));
+
+    let mut date_parts = date.splitn(3, '-').map(|x| x.parse::<u32>().ok());
+    let (y, m, d) = (date_parts.next()??, date_parts.next()??, date_parts.next()??);
+    if !(1970..=9999).contains(&y) || !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return None;
+    }
+
+    let (hms, frac) = time.split_once('.').unwrap_or((time, ""));
+    let mut time_parts = hms.splitn(3, ':').map(|x| x.parse::<u64>().ok());
+    let (hh, mm, ss) = (time_parts.next()??, time_parts.next()??, time_parts.next().unwrap_or(Some(0))?);
+    if hh > 23 || mm > 59 || ss > 60 {
+        return None;
+    }
+    let ms = if frac.is_empty() {
+        0
+    } else {
+        let digits: String = frac.chars().chain("000".chars()).take(3).collect();
+        digits.parse::<u64>().ok()?
+    };
+
+    let days = days_from_civil(y as i64, m, d) as u64;
+    Some(((days * 86400 + hh * 3600 + mm * 60 + ss) * 1000) + ms)
+}
+
+// Howard Hinnant's civil calendar conversions (proleptic Gregorian)
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + (m <= 2) as i64;
+    (y, m, d)
+}
+
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (m as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
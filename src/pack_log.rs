@@ -0,0 +1,162 @@
+// pack_log.rs - Pack rendering for tracing diagnostics
+//
+// Packs carry credentials (hashed and plain passwords, session keys, tickets),
+// so anything that logs a pack goes through RedactedPack, which prints the
+// shape of secret elements but never their values. Debug for Pack and
+// Element uses the same redaction, so {:?} and tracing's ?pack are safe too.
+
+use crate::pack_types::*;
+use std::fmt;
+
+// Element names whose values must never appear in logs (compared case-insensitively)
+pub const SECRET_ELEMENT_NAMES: &[&str] = &[
+    "password",
+    "secure_password",
+    "plain_password",
+    "hashed_password",
+    "session_key",
+    "session_key_32",
+    "ticket",
+    "sign",
+    "priv_key",
+    "udp_acceleration_server_key",
+    "udp_acceleration_server_key_v2",
+];
+
+// Data values longer than this are truncated
+const DATA_PREVIEW_LEN: usize = 16;
+
+// IsSecretElementName
+pub fn is_secret_element_name(name: &str) -> bool {
+    SECRET_ELEMENT_NAMES.iter().any(|s| s.eq_ignore_ascii_case(name))
+}
+
+// RedactedPack - Display for a pack with secret values hidden
+pub struct RedactedPack<'a>(&'a Pack);
+
+impl Pack {
+    // Redacted - a view of the pack that is safe to log
+    pub fn redacted(&self) -> RedactedPack<'_> {
+        RedactedPack(self)
+    }
+}
+
+impl fmt::Display for RedactedPack<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{")?;
+        for (i, e) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: ", e.name)?;
+            if e.num_value() > 1 {
+                write!(f, "[")?;
+            }
+            for (j, v) in e.values.iter().enumerate() {
+                if j > 0 {
+                    write!(f, ", ")?;
+                }
+                if is_secret_element_name(&e.name) {
+                    write_redacted(f, v)?;
+                } else {
+                    write_value(f, v)?;
+                }
+            }
+            if e.num_value() > 1 {
+                write!(f, "]")?;
+            }
+        }
+        write!(f, "}}")
+    }
+}
+
+impl fmt::Debug for RedactedPack<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+fn write_value(f: &mut fmt::Formatter<'_>, v: &Value) -> fmt::Result {
+    match v {
+        Value::Int(i) => write!(f, "{}", i),
+        Value::Int64(i) => write!(f, "{}", i),
+        Value::Str(s) | Value::UniStr(s) => write!(f, "{:?}", s),
+        Value::Data(d) => {
+            for b in d.iter().take(DATA_PREVIEW_LEN) {
+                write!(f, "{:02x}", b)?;
+            }
+            if d.len() > DATA_PREVIEW_LEN {
+                write!(f, "..({} bytes)", d.len())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn write_redacted(f: &mut fmt::Formatter<'_>, v: &Value) -> fmt::Result {
+    match v {
+        Value::Data(d) => fmt::Debug::fmt(&Secret::bytes(d), f),
+        Value::Str(s) | Value::UniStr(s) => fmt::Debug::fmt(&Secret::chars(s), f),
+        Value::Int(_) | Value::Int64(_) => write!(f, "<redacted>"),
+    }
+}
+
+// Secret - stands in for a credential in a hand-written Debug impl
+pub(crate) enum Secret {
+    Bytes(usize),
+    Chars(usize),
+}
+
+impl Secret {
+    pub(crate) fn bytes(d: &[u8]) -> Self {
+        Secret::Bytes(d.len())
+    }
+
+    pub(crate) fn chars(s: &str) -> Self {
+        Secret::Chars(s.chars().count())
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Secret::Bytes(n) => write!(f, "<redacted {} bytes>", n),
+            Secret::Chars(n) => write!(f, "<redacted {} chars>", n),
+        }
+    }
+}
+
+impl fmt::Debug for Pack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Pack {}", self.redacted())
+    }
+}
+
+impl fmt::Debug for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Values<'a>(&'a Element);
+
+        impl fmt::Debug for Values<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                if !is_secret_element_name(&self.0.name) {
+                    return self.0.values.fmt(f);
+                }
+                f.debug_list().entries(self.0.values.iter().map(Redacted)).finish()
+            }
+        }
+
+        struct Redacted<'a>(&'a Value);
+
+        impl fmt::Debug for Redacted<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write_redacted(f, self.0)
+            }
+        }
+
+        f.debug_struct("Element")
+            .field("name", &self.name)
+            .field("type_", &self.type_)
+            .field("values", &Values(self))
+            .finish_non_exhaustive()
+    }
+}
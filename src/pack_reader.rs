@@ -1,28 +1,42 @@
 // pack_reader.rs - Exact translation of pack_reader.go
 
 use crate::pack_types::*;
-use crate::memory::read_buf_str;
-use std::io::Read;
+use std::io::{self, Read};
+use tracing::{field, trace, trace_span};
+
+// Values are read in chunks of at most this size, so memory is only
+// committed as data actually arrives rather than up front from the size field
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+// Initial capacity cap for element and value lists taken from counts on the wire
+const RESERVE_MAX: usize = 64;
 
 // ReadPack read pack from buf
 pub fn read_pack<R: Read>(r: &mut R) -> Result<Pack, PackError> {
+    read_pack_with_limits(r, &PackLimits::default())
+}
+
+// ReadPackWithLimits read pack from buf, enforcing limits as the input is consumed
+pub fn read_pack_with_limits<R: Read>(r: &mut R, limits: &PackLimits) -> Result<Pack, PackError> {
+    let span = trace_span!("read_pack", elements = field::Empty);
+    let _enter = span.enter();
+
+    let mut reader = LimitedReader::new(r, limits);
     let mut pack = Pack::new();
-    
-    let mut num_bytes = [0u8; 4];
-    r.read_exact(&mut num_bytes).map_err(|_| PackError::IoError)?;
-    let num = u32::from_be_bytes(num_bytes);
-    
-    println!("[DEBUG] Pack has {} elements (max allowed: {})", num, MAX_ELEMENT_NUM);
-    
-    if num > MAX_ELEMENT_NUM {
-        return Err(PackError::NumberExceeds);
+
+    let num = reader.read_u32()?;
+    span.record("elements", num);
+
+    if num > limits.max_elements {
+        return Err(PackError::new(PackErrorKind::NumberExceeds).at(0));
     }
 
-    pack.elements.reserve(num as usize);
+    pack.elements.reserve((num as usize).min(RESERVE_MAX));
 
     for _ in 0..num {
-        let e = read_element(r)?;
-        pack.add_element(e)?;
+        let start = reader.pos;
+        let e = reader.read_element()?;
+        pack.add_element(e).map_err(|e| e.at(start))?;
     }
 
     Ok(pack)
@@ -30,83 +44,180 @@ pub fn read_pack<R: Read>(r: &mut R) -> Result<Pack, PackError> {
 
 // ReadElement read element from a reader
 pub fn read_element<R: Read>(r: &mut R) -> Result<Element, PackError> {
-    let name = read_buf_str(r).map_err(|_| PackError::IoError)?;
-
-    let mut type_bytes = [0u8; 4];
-    r.read_exact(&mut type_bytes).map_err(|_| PackError::IoError)?;
-    let element_type = ValueType::from_u32(u32::from_be_bytes(type_bytes))
-        .ok_or(PackError::InvalidType)?;
-
-    let mut n_bytes = [0u8; 4];
-    r.read_exact(&mut n_bytes).map_err(|_| PackError::IoError)?;
-    let n = u32::from_be_bytes(n_bytes);
-    
-    println!("[DEBUG] Element '{}' has {} values (max allowed: {})", name, n, MAX_VALUE_NUM);
-    
-    if n > MAX_VALUE_NUM {
-        return Err(PackError::NumberExceeds);
+    LimitedReader::new(r, &PackLimits::default()).read_element()
+}
+
+// ReadValue read value from a reader
+pub fn read_value<R: Read>(r: &mut R, t: ValueType) -> Result<Value, PackError> {
+    LimitedReader::new(r, &PackLimits::default()).read_value(t)
+}
+
+// Reader that charges every byte against the total size budget and tracks
+// the stream offset for error reports
+struct LimitedReader<'a, R: Read> {
+    r: &'a mut R,
+    limits: &'a PackLimits,
+    remaining: u64,
+    pos: u64,
+}
+
+impl<'a, R: Read> LimitedReader<'a, R> {
+    fn new(r: &'a mut R, limits: &'a PackLimits) -> Self {
+        Self {
+            r,
+            limits,
+            remaining: limits.max_total_size,
+            pos: 0,
+        }
     }
 
-    let mut values = Vec::with_capacity(n as usize);
-    for _ in 0..n {
-        let v = read_value(r, element_type)?;
-        values.push(v);
+    fn error(&self, kind: PackErrorKind) -> PackError {
+        PackError::new(kind).at(self.pos)
     }
 
-    Ok(Element {
-        name,
-        type_: element_type,
-        values,
-        json_hint_is_array: false,
-        json_hint_is_bool: false,
-        json_hint_is_date_time: false,
-        json_hint_is_ip: false,
-        json_hint_group_name: String::new(),
-    })
-}
+    fn consume(&mut self, n: u64) -> Result<(), PackError> {
+        if n > self.remaining {
+            return Err(self.error(PackErrorKind::SizeOver));
+        }
+        self.remaining -= n;
+        self.pos += n;
+        Ok(())
+    }
 
-// ReadValue read value from a reader
-pub fn read_value<R: Read>(r: &mut R, t: ValueType) -> Result<Value, PackError> {
-    match t {
-        ValueType::Int => {
-            let mut bytes = [0u8; 4];
-            r.read_exact(&mut bytes).map_err(|_| PackError::IoError)?;
-            Ok(Value::Int(u32::from_be_bytes(bytes)))
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], PackError> {
+        let at = self.pos;
+        self.consume(N as u64)?;
+        let mut bytes = [0u8; N];
+        self.r.read_exact(&mut bytes).map_err(|e| PackError::io(e).at(at))?;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, PackError> {
+        Ok(u32::from_be_bytes(self.read_array()?))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, PackError> {
+        Ok(u64::from_be_bytes(self.read_array()?))
+    }
+
+    fn read_bytes(&mut self, n: u32) -> Result<Vec<u8>, PackError> {
+        let at = self.pos;
+        self.consume(n as u64)?;
+        let mut data = Vec::with_capacity((n as usize).min(READ_CHUNK_SIZE));
+        (&mut self.r)
+            .take(n as u64)
+            .read_to_end(&mut data)
+            .map_err(|e| PackError::io(e).at(at + data.len() as u64))?;
+        if data.len() != n as usize {
+            let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+            return Err(PackError::io(eof).at(at + data.len() as u64));
         }
-        ValueType::Int64 => {
-            let mut bytes = [0u8; 8];
-            r.read_exact(&mut bytes).map_err(|_| PackError::IoError)?;
-            Ok(Value::Int64(u64::from_be_bytes(bytes)))
+        Ok(data)
+    }
+
+    fn read_string(&mut self, n: u32) -> Result<String, PackError> {
+        let at = self.pos;
+        let data = self.read_bytes(n)?;
+        String::from_utf8(data).map_err(|e| {
+            let e = e.utf8_error();
+            PackError::utf8(e).at(at + e.valid_up_to() as u64)
+        })
+    }
+
+    // ReadBufStr - the size counts a terminator that is not sent
+    fn read_name(&mut self) -> Result<String, PackError> {
+        let at = self.pos;
+        let num = self.read_u32()?;
+        if num == 0 {
+            let e = io::Error::new(io::ErrorKind::InvalidData, "zero-length element name");
+            return Err(PackError::io(e).at(at));
         }
-        ValueType::Data => {
-            let mut s_bytes = [0u8; 4];
-            r.read_exact(&mut s_bytes).map_err(|_| PackError::IoError)?;
-            let s = u32::from_be_bytes(s_bytes);
-            
-            if s > MAX_VALUE_SIZE {
-                return Err(PackError::SizeOver);
-            }
-            
-            let mut data = vec![0u8; s as usize];
-            r.read_exact(&mut data).map_err(|_| PackError::IoError)?;
-            Ok(Value::Data(data))
+        if num - 1 > self.limits.max_name_len {
+            return Err(PackError::new(PackErrorKind::SizeOver).at(at));
         }
-        ValueType::Str => {
-            let mut s_bytes = [0u8; 4];
-            r.read_exact(&mut s_bytes).map_err(|_| PackError::IoError)?;
-            let s = u32::from_be_bytes(s_bytes);
-            
-            if s > MAX_VALUE_SIZE - 1 {
-                return Err(PackError::SizeOver);
-            }
-            
-            let mut data = vec![0u8; s as usize];
-            r.read_exact(&mut data).map_err(|_| PackError::IoError)?;
-            let string = String::from_utf8(data).map_err(|_| PackError::IoError)?;
-            Ok(Value::Str(string))
+        self.read_string(num - 1)
+    }
+
+    fn read_element(&mut self) -> Result<Element, PackError> {
+        let name = self.read_name()?;
+        self.read_element_body(name.clone())
+            .map_err(|e| e.in_element(&name))
+    }
+
+    fn read_element_body(&mut self, name: String) -> Result<Element, PackError> {
+        let at = self.pos;
+        let code = self.read_u32()?;
+        let element_type = ValueType::from_u32(code).ok_or_else(|| PackError::invalid_type(code).at(at))?;
+
+        let at = self.pos;
+        let n = self.read_u32()?;
+
+        trace!(name = %name, type_ = ?element_type, values = n, "element");
+
+        if n > self.limits.max_values_per_element {
+            return Err(PackError::new(PackErrorKind::NumberExceeds).at(at));
         }
-        ValueType::UniStr => {
-            panic!("unimplemented");
+
+        let mut values = Vec::with_capacity((n as usize).min(RESERVE_MAX));
+        for _ in 0..n {
+            let v = self.read_value(element_type)?;
+            values.push(v);
+        }
+
+        Ok(Element {
+            name,
+            type_: element_type,
+            values,
+            json_hint_is_array: false,
+            json_hint_is_bool: false,
+            json_hint_is_date_time: false,
+            json_hint_is_ip: false,
+            json_hint_group_name: String::new(),
+        })
+    }
+
+    fn read_value(&mut self, t: ValueType) -> Result<Value, PackError> {
+        let at = self.pos;
+        match t {
+            ValueType::Int => Ok(Value::Int(self.read_u32()?)),
+            ValueType::Int64 => Ok(Value::Int64(self.read_u64()?)),
+            ValueType::Data => {
+                let s = self.read_u32()?;
+
+                if s > self.limits.max_value_size {
+                    return Err(PackError::new(PackErrorKind::SizeOver).at(at));
+                }
+
+                Ok(Value::Data(self.read_bytes(s)?))
+            }
+            ValueType::Str => {
+                let s = self.read_u32()?;
+
+                if s > self.limits.max_value_size.saturating_sub(1) {
+                    return Err(PackError::new(PackErrorKind::SizeOver).at(at));
+                }
+
+                Ok(Value::Str(self.read_string(s)?))
+            }
+            ValueType::UniStr => {
+                let s = self.read_u32()?;
+
+                if s > self.limits.max_value_size {
+                    return Err(PackError::new(PackErrorKind::SizeOver).at(at));
+                }
+
+                // The size counts the trailing NUL (an empty string is sent as a
+                // lone NUL, or as size 0 by some peers); drop it and anything after
+                let at = self.pos;
+                let mut data = self.read_bytes(s)?;
+                if let Some(pos) = data.iter().position(|&b| b == 0) {
+                    data.truncate(pos);
+                }
+                String::from_utf8(data).map(Value::UniStr).map_err(|e| {
+                    let e = e.utf8_error();
+                    PackError::utf8(e).at(at + e.valid_up_to() as u64)
+                })
+            }
         }
     }
 }
@@ -0,0 +1,327 @@
+// pack_ref.rs - Zero-copy pack view over a received buffer
+//
+// PackRef parses the same wire format as read_pack, but element names and
+// Str/UniStr/Data values borrow from the input buffer instead of being copied.
+
+use crate::pack_types::*;
+use std::collections::HashSet;
+use std::io;
+
+// ValueRef - borrowed counterpart of Value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueRef<'a> {
+    Int(u32),
+    Data(&'a [u8]),
+    Str(&'a str),
+    UniStr(&'a str),
+    Int64(u64),
+}
+
+impl ValueRef<'_> {
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            ValueRef::Int(_) => ValueType::Int,
+            ValueRef::Data(_) => ValueType::Data,
+            ValueRef::Str(_) => ValueType::Str,
+            ValueRef::UniStr(_) => ValueType::UniStr,
+            ValueRef::Int64(_) => ValueType::Int64,
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match *self {
+            ValueRef::Int(v) => Value::Int(v),
+            ValueRef::Data(v) => Value::Data(v.to_vec()),
+            ValueRef::Str(v) => Value::Str(v.to_string()),
+            ValueRef::UniStr(v) => Value::UniStr(v.to_string()),
+            ValueRef::Int64(v) => Value::Int64(v),
+        }
+    }
+}
+
+// ElementRef - borrowed counterpart of Element
+#[derive(Debug, Clone)]
+pub struct ElementRef<'a> {
+    pub name: &'a str,
+    pub type_: ValueType,
+    pub values: Vec<ValueRef<'a>>,
+}
+
+impl<'a> ElementRef<'a> {
+    pub fn num_value(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn get_int_value(&self, index: u32) -> u32 {
+        match self.values.get(index as usize) {
+            Some(ValueRef::Int(v)) => *v,
+            _ => 0,
+        }
+    }
+
+    pub fn get_int64_value(&self, index: u32) -> u64 {
+        match self.values.get(index as usize) {
+            Some(ValueRef::Int64(v)) => *v,
+            _ => 0,
+        }
+    }
+
+    pub fn get_str_value(&self, index: u32) -> &'a str {
+        match self.values.get(index as usize) {
+            Some(ValueRef::Str(v)) => v,
+            _ => "",
+        }
+    }
+
+    pub fn get_uni_str_value(&self, index: u32) -> &'a str {
+        match self.values.get(index as usize) {
+            Some(ValueRef::UniStr(v)) => v,
+            _ => "",
+        }
+    }
+
+    pub fn get_data_value(&self, index: u32) -> &'a [u8] {
+        match self.values.get(index as usize) {
+            Some(ValueRef::Data(v)) => v,
+            _ => &[],
+        }
+    }
+
+    pub fn to_element(&self) -> Element {
+        let mut e = Element::new(self.name.to_string(), self.type_);
+        e.values = self.values.iter().map(ValueRef::to_value).collect();
+        e
+    }
+}
+
+// PackRef - borrowed counterpart of Pack
+#[derive(Debug, Clone)]
+pub struct PackRef<'a> {
+    pub elements: Vec<ElementRef<'a>>,
+}
+
+impl<'a> PackRef<'a> {
+    // Parse parse a pack from buf without copying names or values
+    pub fn parse(buf: &'a [u8]) -> Result<Self, PackError> {
+        Self::parse_with_limits(buf, &PackLimits::default())
+    }
+
+    // ParseWithLimits parse a pack from buf, enforcing limits
+    pub fn parse_with_limits(buf: &'a [u8], limits: &PackLimits) -> Result<Self, PackError> {
+        let mut r = SliceReader { buf, pos: 0, limits };
+
+        let num = r.read_u32()?;
+        if num > limits.max_elements {
+            return Err(PackError::new(PackErrorKind::NumberExceeds).at(0));
+        }
+
+        // Every element takes at least 13 bytes, so cap the reservation by what's left
+        let mut elements = Vec::with_capacity((num as usize).min(r.remaining() / 13));
+        let mut names = HashSet::with_capacity(elements.capacity());
+        for _ in 0..num {
+            let start = r.pos as u64;
+            let e = r.read_element()?;
+            if e.values.is_empty() {
+                return Err(PackError::new(PackErrorKind::ZeroNumValue).in_element(e.name).at(start));
+            }
+            if !names.insert(e.name) {
+                return Err(PackError::new(PackErrorKind::SameNameExists).in_element(e.name).at(start));
+            }
+            elements.push(e);
+        }
+
+        Ok(Self { elements })
+    }
+
+    pub fn get_num(&self) -> usize {
+        self.elements.len()
+    }
+
+    // GetElement - same case-insensitive lookup as Pack::get_element
+    pub fn get_element(&self, name: &str, t: Option<ValueType>) -> Option<&ElementRef<'a>> {
+        self.elements
+            .iter()
+            .find(|e| names_equal(name, e.name) && (t.is_none() || t == Some(e.type_)))
+    }
+
+    pub fn get_int(&self, name: &str) -> u32 {
+        self.get_int_ex(name, 0)
+    }
+
+    pub fn get_int_ex(&self, name: &str, index: u32) -> u32 {
+        self.get_element(name, Some(ValueType::Int))
+            .map_or(0, |e| e.get_int_value(index))
+    }
+
+    pub fn get_int64(&self, name: &str) -> u64 {
+        self.get_int64_ex(name, 0)
+    }
+
+    pub fn get_int64_ex(&self, name: &str, index: u32) -> u64 {
+        self.get_element(name, Some(ValueType::Int64))
+            .map_or(0, |e| e.get_int64_value(index))
+    }
+
+    pub fn get_bool(&self, name: &str) -> bool {
+        self.get_int(name) != 0
+    }
+
+    pub fn get_str(&self, name: &str) -> &'a str {
+        self.get_str_ex(name, 0)
+    }
+
+    pub fn get_str_ex(&self, name: &str, index: u32) -> &'a str {
+        self.get_element(name, Some(ValueType::Str))
+            .map_or("", |e| e.get_str_value(index))
+    }
+
+    pub fn get_uni_str(&self, name: &str) -> &'a str {
+        self.get_uni_str_ex(name, 0)
+    }
+
+    pub fn get_uni_str_ex(&self, name: &str, index: u32) -> &'a str {
+        self.get_element(name, Some(ValueType::UniStr))
+            .map_or("", |e| e.get_uni_str_value(index))
+    }
+
+    pub fn get_data(&self, name: &str) -> &'a [u8] {
+        self.get_data_ex(name, 0)
+    }
+
+    pub fn get_data_ex(&self, name: &str, index: u32) -> &'a [u8] {
+        self.get_element(name, Some(ValueType::Data))
+            .map_or(&[], |e| e.get_data_value(index))
+    }
+
+    // ToOwned copy the view into an owned Pack
+    pub fn to_owned(&self) -> Pack {
+        let mut pack = Pack::new();
+        for e in &self.elements {
+            // Only a hand-built PackRef can hold duplicates; the first one wins
+            let _ = pack.add_element(e.to_element());
+        }
+        pack
+    }
+}
+
+// Cursor over the input buffer; every read is bounds-checked
+struct SliceReader<'a, 'l> {
+    buf: &'a [u8],
+    pos: usize,
+    limits: &'l PackLimits,
+}
+
+impl<'a> SliceReader<'a, '_> {
+    fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    fn error(&self, kind: PackErrorKind) -> PackError {
+        PackError::new(kind).at(self.pos as u64)
+    }
+
+    fn read_bytes(&mut self, n: usize) -> Result<&'a [u8], PackError> {
+        if self.pos as u64 + n as u64 > self.limits.max_total_size {
+            return Err(self.error(PackErrorKind::SizeOver));
+        }
+        if n > self.remaining() {
+            let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+            return Err(PackError::io(eof).at(self.buf.len() as u64));
+        }
+        let b = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(b)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, PackError> {
+        let b = self.read_bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, PackError> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.read_bytes(8)?);
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    fn read_str(&mut self, n: usize) -> Result<&'a str, PackError> {
+        let at = self.pos as u64;
+        to_str(self.read_bytes(n)?, at)
+    }
+
+    // ReadBufStr - the size counts a terminator that is not sent
+    fn read_name(&mut self) -> Result<&'a str, PackError> {
+        let at = self.pos as u64;
+        let num = self.read_u32()?;
+        if num == 0 {
+            let e = io::Error::new(io::ErrorKind::InvalidData, "zero-length element name");
+            return Err(PackError::io(e).at(at));
+        }
+        if num - 1 > self.limits.max_name_len {
+            return Err(PackError::new(PackErrorKind::SizeOver).at(at));
+        }
+        self.read_str(num as usize - 1)
+    }
+
+    fn read_element(&mut self) -> Result<ElementRef<'a>, PackError> {
+        let name = self.read_name()?;
+        self.read_element_body(name).map_err(|e| e.in_element(name))
+    }
+
+    fn read_element_body(&mut self, name: &'a str) -> Result<ElementRef<'a>, PackError> {
+        let at = self.pos as u64;
+        let code = self.read_u32()?;
+        let type_ = ValueType::from_u32(code).ok_or_else(|| PackError::invalid_type(code).at(at))?;
+
+        let at = self.pos as u64;
+        let n = self.read_u32()?;
+        if n > self.limits.max_values_per_element {
+            return Err(PackError::new(PackErrorKind::NumberExceeds).at(at));
+        }
+
+        // Every value takes at least 4 bytes
+        let mut values = Vec::with_capacity((n as usize).min(self.remaining() / 4));
+        for _ in 0..n {
+            values.push(self.read_value(type_)?);
+        }
+
+        Ok(ElementRef { name, type_, values })
+    }
+
+    fn read_value(&mut self, t: ValueType) -> Result<ValueRef<'a>, PackError> {
+        let at = self.pos as u64;
+        match t {
+            ValueType::Int => Ok(ValueRef::Int(self.read_u32()?)),
+            ValueType::Int64 => Ok(ValueRef::Int64(self.read_u64()?)),
+            ValueType::Data => {
+                let s = self.read_u32()?;
+                if s > self.limits.max_value_size {
+                    return Err(PackError::new(PackErrorKind::SizeOver).at(at));
+                }
+                Ok(ValueRef::Data(self.read_bytes(s as usize)?))
+            }
+            ValueType::Str => {
+                let s = self.read_u32()?;
+                if s > self.limits.max_value_size.saturating_sub(1) {
+                    return Err(PackError::new(PackErrorKind::SizeOver).at(at));
+                }
+                Ok(ValueRef::Str(self.read_str(s as usize)?))
+            }
+            ValueType::UniStr => {
+                let s = self.read_u32()?;
+                if s > self.limits.max_value_size {
+                    return Err(PackError::new(PackErrorKind::SizeOver).at(at));
+                }
+                // Same NUL handling as read_value
+                let at = self.pos as u64;
+                let b = self.read_bytes(s as usize)?;
+                let b = &b[..b.iter().position(|&c| c == 0).unwrap_or(b.len())];
+                Ok(ValueRef::UniStr(to_str(b, at)?))
+            }
+        }
+    }
+}
+
+fn to_str(b: &[u8], at: u64) -> Result<&str, PackError> {
+    std::str::from_utf8(b).map_err(|e| PackError::utf8(e).at(at + e.valid_up_to() as u64))
+}
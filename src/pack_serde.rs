@@ -0,0 +1,887 @@
+// pack_serde.rs - serde data format for Pack
+//
+// A struct maps to a pack with one element per field:
+//   u8/u16/u32/i8/i16/i32 -> Int, u64/i64 -> Int64, bool -> Int (JsonHint_IsBool),
+//   String/&str/char -> Str, unit enum variants -> Str (variant name).
+// Option<T> fields are omitted when None, and Vec<T> fields become a
+// multi-value element (JsonHint_IsArray), omitted when empty. Use the helper modules below with
+// #[serde(with = "...")] for Data (`bytes`), UniStr (`uni_str`), IP
+// triplets (`ip`) and date-times (`time64`); a plain Vec<u8> is an array of
+// Int values.
+
+use crate::pack_types::*;
+use serde::de::{self, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Impossible, Serialize, Serializer};
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::net::IpAddr;
+
+const UNI_STR_TOKEN: &str = "$mayaqua::UniStr";
+const IP_TOKEN: &str = "$mayaqua::Ip";
+const TIME64_TOKEN: &str = "$mayaqua::Time64";
+
+// PackSerdeError - serialization error
+#[derive(Debug, Clone)]
+pub enum PackSerdeError {
+    Message(String),
+    UnsupportedType(&'static str),
+    Pack(PackError),
+    MissingField(&'static str),
+}
+
+impl fmt::Display for PackSerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PackSerdeError::Message(msg) => write!(f, "{}", msg),
+            PackSerdeError::UnsupportedType(t) => write!(f, "Unsupported type: {}", t),
+            PackSerdeError::Pack(e) => write!(f, "Pack error: {}", e),
+            PackSerdeError::MissingField(name) => write!(f, "missing field `{}`", name),
+        }
+    }
+}
+
+impl Error for PackSerdeError {}
+
+impl ser::Error for PackSerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        PackSerdeError::Message(msg.to_string())
+    }
+}
+
+impl de::Error for PackSerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        PackSerdeError::Message(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        PackSerdeError::MissingField(field)
+    }
+}
+
+impl From<PackError> for PackSerdeError {
+    fn from(e: PackError) -> Self {
+        PackSerdeError::Pack(e)
+    }
+}
+
+// ToPack serialize a struct (or string-keyed map) into a pack
+pub fn to_pack<T: Serialize + ?Sized>(value: &T) -> Result<Pack, PackSerdeError> {
+    let mut pack = Pack::new();
+    value.serialize(PackSerializer { pack: &mut pack })?;
+    Ok(pack)
+}
+
+// FromPack deserialize a struct from a pack
+pub fn from_pack<'de, T: Deserialize<'de>>(pack: &'de Pack) -> Result<T, PackSerdeError> {
+    // An empty Vec has no element on the wire; when serde reports such a
+    // field missing, try again offering it as an empty sequence. Fields of
+    // other types are reported missing again and the loop stops.
+    let mut empty = Vec::new();
+    loop {
+        match T::deserialize(PackDeserializer { pack, empty: &empty }) {
+            Err(PackSerdeError::MissingField(name)) if !empty.contains(&name) => empty.push(name),
+            r => return r,
+        }
+    }
+}
+
+// Data value: #[serde(with = "mayaqua::pack_serde::bytes")] on a Vec<u8>
+pub mod bytes {
+    use serde::de::{Deserializer, SeqAccess, Visitor};
+    use serde::ser::Serializer;
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(v: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_bytes(v)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = Vec<u8>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a byte array")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+                Ok(v.to_vec())
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+                Ok(v)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+                let mut v = Vec::new();
+                while let Some(b) = seq.next_element()? {
+                    v.push(b);
+                }
+                Ok(v)
+            }
+        }
+
+        d.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+// UniStr value: #[serde(with = "mayaqua::pack_serde::uni_str")] on a String
+pub mod uni_str {
+    use super::UNI_STR_TOKEN;
+    use serde::de::{Deserialize, Deserializer, Visitor};
+    use serde::ser::Serializer;
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(v: &str, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_newtype_struct(UNI_STR_TOKEN, v)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+        struct UniStrVisitor;
+
+        impl<'de> Visitor<'de> for UniStrVisitor {
+            type Value = String;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<String, E> {
+                Ok(v.to_string())
+            }
+
+            fn visit_newtype_struct<D: Deserializer<'de>>(self, d: D) -> Result<String, D::Error> {
+                String::deserialize(d)
+            }
+        }
+
+        d.deserialize_newtype_struct(UNI_STR_TOKEN, UniStrVisitor)
+    }
+}
+
+// IP triplet (PackAddIp): #[serde(with = "mayaqua::pack_serde::ip")] on an IpAddr
+pub mod ip {
+    use super::IP_TOKEN;
+    use serde::de::{Deserialize, Deserializer, Error, Visitor};
+    use serde::ser::Serializer;
+    use std::fmt;
+    use std::net::IpAddr;
+
+    pub fn serialize<S: Serializer>(v: &IpAddr, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_newtype_struct(IP_TOKEN, &v.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<IpAddr, D::Error> {
+        struct IpVisitor;
+
+        impl<'de> Visitor<'de> for IpVisitor {
+            type Value = IpAddr;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an IP address")
+            }
+
+            fn visit_str<E: Error>(self, v: &str) -> Result<IpAddr, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_newtype_struct<D: Deserializer<'de>>(self, d: D) -> Result<IpAddr, D::Error> {
+                String::deserialize(d)?.parse().map_err(D::Error::custom)
+            }
+        }
+
+        d.deserialize_newtype_struct(IP_TOKEN, IpVisitor)
+    }
+}
+
+// Date-time (PackAddTime64): #[serde(with = "mayaqua::pack_serde::time64")] on a SystemTime
+pub mod time64 {
+    use super::TIME64_TOKEN;
+    use crate::pack_types::{system_time_to_time64, time64_to_system_time};
+    use serde::de::{Deserialize, Deserializer, Error, Visitor};
+    use serde::ser::Serializer;
+    use std::fmt;
+    use std::time::SystemTime;
+
+    pub fn serialize<S: Serializer>(v: &SystemTime, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_newtype_struct(TIME64_TOKEN, &system_time_to_time64(*v))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<SystemTime, D::Error> {
+        struct Time64Visitor;
+
+        impl<'de> Visitor<'de> for Time64Visitor {
+            type Value = SystemTime;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("milliseconds since the Unix epoch")
+            }
+
+            fn visit_u64<E: Error>(self, v: u64) -> Result<SystemTime, E> {
+                time64_to_system_time(v).ok_or_else(|| E::custom(format!("time64 {} is out of range", v)))
+            }
+
+            fn visit_newtype_struct<D: Deserializer<'de>>(self, d: D) -> Result<SystemTime, D::Error> {
+                self.visit_u64(u64::deserialize(d)?)
+            }
+        }
+
+        d.deserialize_newtype_struct(TIME64_TOKEN, Time64Visitor)
+    }
+}
+
+// === Serializer ===
+
+// A single field value before it is placed into the pack
+enum Scalar {
+    Value(Value, Hint),
+    Ip(IpAddr),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Hint {
+    None,
+    Bool,
+    DateTime,
+}
+
+fn add_scalar(
+    pack: &mut Pack,
+    name: &str,
+    scalar: Scalar,
+    index: u32,
+    total: u32,
+    group: Option<&str>,
+) -> Result<(), PackSerdeError> {
+    match scalar {
+        Scalar::Value(v, hint) => {
+            let e = pack.add_value_ex(name, v, index, total, group)?;
+            e.json_hint_is_bool = hint == Hint::Bool;
+            e.json_hint_is_date_time = hint == Hint::DateTime;
+        }
+        Scalar::Ip(ip) => {
+            pack.add_ip_value_ex(name, ip, 0, index, total, group)?;
+        }
+    }
+    Ok(())
+}
+
+struct PackSerializer<'a> {
+    pack: &'a mut Pack,
+}
+
+macro_rules! unsupported {
+    ($($method:ident($($arg:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ok, PackSerdeError> {
+                Err(PackSerdeError::UnsupportedType(stringify!($method)))
+            }
+        )*
+    };
+}
+
+macro_rules! forward_scalars {
+    ($($method:ident($arg:ty);)*) => {
+        $(
+            fn $method(self, v: $arg) -> Result<(), PackSerdeError> {
+                let scalar = ScalarSerializer.$method(v)?;
+                add_scalar(self.pack, self.name, scalar, 0, 1, None)
+            }
+        )*
+    };
+}
+
+impl<'a> Serializer for PackSerializer<'a> {
+    type Ok = ();
+    type Error = PackSerdeError;
+    type SerializeSeq = Impossible<(), PackSerdeError>;
+    type SerializeTuple = Impossible<(), PackSerdeError>;
+    type SerializeTupleStruct = Impossible<(), PackSerdeError>;
+    type SerializeTupleVariant = Impossible<(), PackSerdeError>;
+    type SerializeMap = StructSerializer<'a>;
+    type SerializeStruct = StructSerializer<'a>;
+    type SerializeStructVariant = Impossible<(), PackSerdeError>;
+
+    unsupported! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, v: &T) -> Result<(), PackSerdeError> {
+        v.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _: &'static str, v: &T) -> Result<(), PackSerdeError> {
+        v.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), PackSerdeError> {
+        Err(PackSerdeError::UnsupportedType("serialize_newtype_variant"))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<StructSerializer<'a>, PackSerdeError> {
+        Ok(StructSerializer { pack: self.pack, key: None })
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<StructSerializer<'a>, PackSerdeError> {
+        Ok(StructSerializer { pack: self.pack, key: None })
+    }
+}
+
+struct StructSerializer<'a> {
+    pack: &'a mut Pack,
+    key: Option<String>,
+}
+
+impl ser::SerializeStruct for StructSerializer<'_> {
+    type Ok = ();
+    type Error = PackSerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, v: &T) -> Result<(), PackSerdeError> {
+        v.serialize(FieldSerializer { pack: self.pack, name: key })
+    }
+
+    fn end(self) -> Result<(), PackSerdeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for StructSerializer<'_> {
+    type Ok = ();
+    type Error = PackSerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), PackSerdeError> {
+        match key.serialize(ScalarSerializer)? {
+            Scalar::Value(Value::Str(s), _) => {
+                self.key = Some(s);
+                Ok(())
+            }
+            _ => Err(PackSerdeError::UnsupportedType("non-string map key")),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), PackSerdeError> {
+        let key = self.key.take().ok_or(PackSerdeError::UnsupportedType("map value without key"))?;
+        v.serialize(FieldSerializer { pack: self.pack, name: &key })
+    }
+
+    fn end(self) -> Result<(), PackSerdeError> {
+        Ok(())
+    }
+}
+
+// Serializes one struct field into an element
+struct FieldSerializer<'a> {
+    pack: &'a mut Pack,
+    name: &'a str,
+}
+
+impl<'a> Serializer for FieldSerializer<'a> {
+    type Ok = ();
+    type Error = PackSerdeError;
+    type SerializeSeq = SeqSerializer<'a>;
+    type SerializeTuple = SeqSerializer<'a>;
+    type SerializeTupleStruct = Impossible<(), PackSerdeError>;
+    type SerializeTupleVariant = Impossible<(), PackSerdeError>;
+    type SerializeMap = Impossible<(), PackSerdeError>;
+    type SerializeStruct = Impossible<(), PackSerdeError>;
+    type SerializeStructVariant = Impossible<(), PackSerdeError>;
+
+    forward_scalars! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+        serialize_bytes(&[u8]);
+    }
+
+    unsupported! {
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_none(self) -> Result<(), PackSerdeError> {
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, v: &T) -> Result<(), PackSerdeError> {
+        v.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), PackSerdeError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), PackSerdeError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, index: u32, variant: &'static str) -> Result<(), PackSerdeError> {
+        let scalar = ScalarSerializer.serialize_unit_variant(name, index, variant)?;
+        add_scalar(self.pack, self.name, scalar, 0, 1, None)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, v: &T) -> Result<(), PackSerdeError> {
+        let scalar = ScalarSerializer.serialize_newtype_struct(name, v)?;
+        add_scalar(self.pack, self.name, scalar, 0, 1, None)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), PackSerdeError> {
+        Err(PackSerdeError::UnsupportedType("serialize_newtype_variant"))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer<'a>, PackSerdeError> {
+        Ok(SeqSerializer { pack: self.pack, name: self.name, items: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer<'a>, PackSerdeError> {
+        self.serialize_seq(Some(len))
+    }
+}
+
+// Collects the values of a Vec field into one multi-value element
+struct SeqSerializer<'a> {
+    pack: &'a mut Pack,
+    name: &'a str,
+    items: Vec<Scalar>,
+}
+
+impl ser::SerializeSeq for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = PackSerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), PackSerdeError> {
+        self.items.push(v.serialize(ScalarSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), PackSerdeError> {
+        let total = self.items.len() as u32;
+        for (i, scalar) in self.items.into_iter().enumerate() {
+            add_scalar(self.pack, self.name, scalar, i as u32, total, Some(""))?;
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = PackSerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), PackSerdeError> {
+        ser::SerializeSeq::serialize_element(self, v)
+    }
+
+    fn end(self) -> Result<(), PackSerdeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+// Serializes a single value into a Scalar
+struct ScalarSerializer;
+
+impl Serializer for ScalarSerializer {
+    type Ok = Scalar;
+    type Error = PackSerdeError;
+    type SerializeSeq = Impossible<Scalar, PackSerdeError>;
+    type SerializeTuple = Impossible<Scalar, PackSerdeError>;
+    type SerializeTupleStruct = Impossible<Scalar, PackSerdeError>;
+    type SerializeTupleVariant = Impossible<Scalar, PackSerdeError>;
+    type SerializeMap = Impossible<Scalar, PackSerdeError>;
+    type SerializeStruct = Impossible<Scalar, PackSerdeError>;
+    type SerializeStructVariant = Impossible<Scalar, PackSerdeError>;
+
+    fn serialize_bool(self, v: bool) -> Result<Scalar, PackSerdeError> {
+        Ok(Scalar::Value(Value::Int(v as u32), Hint::Bool))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Scalar, PackSerdeError> {
+        self.serialize_i32(v as i32)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Scalar, PackSerdeError> {
+        self.serialize_i32(v as i32)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Scalar, PackSerdeError> {
+        Ok(Scalar::Value(Value::Int(v as u32), Hint::None))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Scalar, PackSerdeError> {
+        Ok(Scalar::Value(Value::Int64(v as u64), Hint::None))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Scalar, PackSerdeError> {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Scalar, PackSerdeError> {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Scalar, PackSerdeError> {
+        Ok(Scalar::Value(Value::Int(v), Hint::None))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Scalar, PackSerdeError> {
+        Ok(Scalar::Value(Value::Int64(v), Hint::None))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Scalar, PackSerdeError> {
+        Ok(Scalar::Value(Value::Str(v.to_string()), Hint::None))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Scalar, PackSerdeError> {
+        Ok(Scalar::Value(Value::Str(v.to_string()), Hint::None))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Scalar, PackSerdeError> {
+        Ok(Scalar::Value(Value::Data(v.to_vec()), Hint::None))
+    }
+
+    fn serialize_unit_variant(self, _: &'static str, _: u32, variant: &'static str) -> Result<Scalar, PackSerdeError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, v: &T) -> Result<Scalar, PackSerdeError> {
+        match (name, v.serialize(ScalarSerializer)?) {
+            (UNI_STR_TOKEN, Scalar::Value(Value::Str(s), _)) => Ok(Scalar::Value(Value::UniStr(s), Hint::None)),
+            (TIME64_TOKEN, Scalar::Value(Value::Int64(t), _)) => Ok(Scalar::Value(Value::Int64(t), Hint::DateTime)),
+            (IP_TOKEN, Scalar::Value(Value::Str(s), _)) => {
+                s.parse().map(Scalar::Ip).map_err(|e| PackSerdeError::Message(format!("{}", e)))
+            }
+            (UNI_STR_TOKEN, _) | (IP_TOKEN, _) | (TIME64_TOKEN, _) => Err(PackSerdeError::UnsupportedType(name)),
+            (_, scalar) => Ok(scalar),
+        }
+    }
+
+    unsupported! {
+        serialize_f32(f32) -> Scalar;
+        serialize_f64(f64) -> Scalar;
+        serialize_none() -> Scalar;
+        serialize_unit() -> Scalar;
+        serialize_unit_struct(&'static str) -> Scalar;
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<Scalar, PackSerdeError> {
+        Err(PackSerdeError::UnsupportedType("serialize_some"))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Scalar, PackSerdeError> {
+        Err(PackSerdeError::UnsupportedType("serialize_newtype_variant"))
+    }
+}
+
+// === Deserializer ===
+
+struct PackDeserializer<'a, 'de> {
+    pack: &'de Pack,
+    // Fields without an element that are deserialized as empty sequences
+    empty: &'a [&'static str],
+}
+
+impl<'de> Deserializer<'de> for PackDeserializer<'_, 'de> {
+    type Error = PackSerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, PackSerdeError> {
+        self.deserialize_map(visitor)
+    }
+
+    // Every element becomes a map entry
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, PackSerdeError> {
+        let names: Vec<&'de str> = self.pack.elements.iter().map(|e| e.name.as_str()).collect();
+        visitor.visit_map(FieldsAccess { pack: self.pack, empty: &[], names: names.into_iter(), current: None })
+    }
+
+    // Only the struct's fields that are present in the pack are visited, so
+    // serde reports missing required fields and defaults Option fields to None
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, PackSerdeError> {
+        let names: Vec<&'de str> = fields.to_vec();
+        visitor.visit_map(FieldsAccess { pack: self.pack, empty: self.empty, names: names.into_iter(), current: None })
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _: &'static str, visitor: V) -> Result<V::Value, PackSerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct enum identifier ignored_any
+    }
+}
+
+struct FieldsAccess<'a, 'de> {
+    pack: &'de Pack,
+    empty: &'a [&'static str],
+    names: std::vec::IntoIter<&'de str>,
+    current: Option<FieldValue<'de>>,
+}
+
+enum FieldValue<'de> {
+    Element(&'de Element),
+    Empty(&'static str),
+}
+
+impl<'de> MapAccess<'de> for FieldsAccess<'_, 'de> {
+    type Error = PackSerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, PackSerdeError> {
+        for name in self.names.by_ref() {
+            self.current = match self.pack.get_element(name, None) {
+                Some(e) => Some(FieldValue::Element(e)),
+                None => self.empty.iter().find(|&&n| n == name).map(|&n| FieldValue::Empty(n)),
+            };
+            if self.current.is_some() {
+                return seed.deserialize(name.into_deserializer()).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, PackSerdeError> {
+        match self.current.take().ok_or(PackSerdeError::UnsupportedType("value without key"))? {
+            FieldValue::Element(element) => seed.deserialize(ElementDeserializer { pack: self.pack, element, index: None }),
+            FieldValue::Empty(name) => seed.deserialize(EmptyDeserializer { name }),
+        }
+    }
+}
+
+// A field serde reported missing: an empty sequence if it is one, and still
+// missing otherwise
+struct EmptyDeserializer {
+    name: &'static str,
+}
+
+impl<'de> Deserializer<'de> for EmptyDeserializer {
+    type Error = PackSerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, PackSerdeError> {
+        Err(PackSerdeError::MissingField(self.name))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, PackSerdeError> {
+        visitor.visit_seq(de::value::SeqDeserializer::new(std::iter::empty::<u32>()))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, PackSerdeError> {
+        visitor.visit_none()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+// Deserializes one element; index is None for the whole element and Some
+// for a single value while walking a sequence
+struct ElementDeserializer<'de> {
+    pack: &'de Pack,
+    element: &'de Element,
+    index: Option<u32>,
+}
+
+impl<'de> ElementDeserializer<'de> {
+    fn value(&self) -> Result<&'de Value, PackSerdeError> {
+        let index = self.index.unwrap_or(0);
+        self.element.values.get(index as usize).ok_or_else(|| {
+            PackSerdeError::Message(format!("element '{}' has no value at index {}", self.element.name, index))
+        })
+    }
+
+    fn visit_number<V: Visitor<'de>>(self, signed: bool, visitor: V) -> Result<V::Value, PackSerdeError> {
+        match self.value()? {
+            Value::Int(v) if signed => visitor.visit_i32(*v as i32),
+            Value::Int64(v) if signed => visitor.visit_i64(*v as i64),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+}
+
+macro_rules! deserialize_numbers {
+    ($($method:ident => $signed:expr;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, PackSerdeError> {
+                self.visit_number($signed, visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ElementDeserializer<'de> {
+    type Error = PackSerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, PackSerdeError> {
+        if self.index.is_none() && self.element.num_value() > 1 {
+            return self.deserialize_seq(visitor);
+        }
+        match self.value()? {
+            Value::Int(v) => visitor.visit_u32(*v),
+            Value::Int64(v) => visitor.visit_u64(*v),
+            Value::Str(s) | Value::UniStr(s) => visitor.visit_borrowed_str(s),
+            Value::Data(d) => visitor.visit_borrowed_bytes(d),
+        }
+    }
+
+    deserialize_numbers! {
+        deserialize_i8 => true;
+        deserialize_i16 => true;
+        deserialize_i32 => true;
+        deserialize_i64 => true;
+        deserialize_u8 => false;
+        deserialize_u16 => false;
+        deserialize_u32 => false;
+        deserialize_u64 => false;
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, PackSerdeError> {
+        match self.value()? {
+            Value::Int(v) => visitor.visit_bool(*v != 0),
+            Value::Int64(v) => visitor.visit_bool(*v != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, PackSerdeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> Result<V::Value, PackSerdeError> {
+        match name {
+            UNI_STR_TOKEN | TIME64_TOKEN => self.deserialize_any(visitor),
+            IP_TOKEN => {
+                let ip = self.pack.get_ip_addr_ex(&self.element.name, self.index.unwrap_or(0)).ok_or_else(|| {
+                    PackSerdeError::Message(format!("element '{}' is not an IP address", self.element.name))
+                })?;
+                visitor.visit_string(ip.to_string())
+            }
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, PackSerdeError> {
+        if self.index.is_some() {
+            return self.deserialize_any(visitor);
+        }
+        visitor.visit_seq(ValuesAccess { pack: self.pack, element: self.element, next: 0 })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, PackSerdeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, PackSerdeError> {
+        match self.value()? {
+            Value::Str(s) | Value::UniStr(s) => visitor.visit_enum(s.as_str().into_deserializer()),
+            _ => Err(PackSerdeError::Message(format!("element '{}' is not a string", self.element.name))),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, PackSerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, PackSerdeError> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 f32 f64 char str string bytes byte_buf unit_struct
+        tuple_struct map struct identifier
+    }
+}
+
+struct ValuesAccess<'de> {
+    pack: &'de Pack,
+    element: &'de Element,
+    next: u32,
+}
+
+impl<'de> SeqAccess<'de> for ValuesAccess<'de> {
+    type Error = PackSerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, PackSerdeError> {
+        if self.next as usize >= self.element.num_value() {
+            return Ok(None);
+        }
+        let index = Some(self.next);
+        self.next += 1;
+        seed.deserialize(ElementDeserializer { pack: self.pack, element: self.element, index }).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.element.num_value() - self.next as usize)
+    }
+}
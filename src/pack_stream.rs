@@ -0,0 +1,131 @@
+// pack_stream.rs - Streaming pack output with Data values taken from readers
+//
+// PackStream writes a pack followed by extra elements, some of which are Data
+// values read from a Read / AsyncRead of known length, so large payloads are
+// never held in memory. The element count and total size are known up front,
+// as the SoftEther framing and HTTP Content-Length both need them.
+
+use crate::pack_async::write_elements_async;
+use crate::pack_types::*;
+use crate::pack_writer::{check_value_types, element_header_len, len_u32, write_element_header};
+use std::io::{self, Read, Write};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+// PackStream - a pack plus appended elements, written in that order
+pub struct PackStream<'p, R> {
+    pack: &'p Pack,
+    extra: Vec<Element>,
+    readers: Vec<(String, u32, R)>,
+}
+
+impl<'p, R> PackStream<'p, R> {
+    pub fn new(pack: &'p Pack) -> Self {
+        Self {
+            pack,
+            extra: Vec::new(),
+            readers: Vec::new(),
+        }
+    }
+
+    // AddElement append an owned element
+    pub fn add_element(&mut self, e: Element) -> Result<(), PackError> {
+        if e.values.is_empty() {
+            return Err(PackError::new(PackErrorKind::ZeroNumValue).in_element(&e.name));
+        }
+        self.check_name(&e.name)?;
+        self.extra.push(e);
+        Ok(())
+    }
+
+    // AddDataReader append a Data element whose len bytes are read from reader
+    pub fn add_data_reader(&mut self, name: &str, len: u32, reader: R) -> Result<(), PackError> {
+        if len > MAX_VALUE_SIZE {
+            return Err(PackError::new(PackErrorKind::SizeOver).in_element(name));
+        }
+        self.check_name(name)?;
+        self.readers.push((name.to_string(), len, reader));
+        Ok(())
+    }
+
+    // Same exact-name duplicate rule as Pack::add_element
+    fn check_name(&self, name: &str) -> Result<(), PackError> {
+        let taken = self.pack.iter().chain(&self.extra).any(|e| e.name == name)
+            || self.readers.iter().any(|(n, _, _)| n == name);
+        if taken {
+            return Err(PackError::new(PackErrorKind::SameNameExists).in_element(name));
+        }
+        Ok(())
+    }
+
+    pub fn get_num(&self) -> usize {
+        self.pack.get_num() + self.extra.len() + self.readers.len()
+    }
+
+    // EncodedLen size in bytes of what write_to / write_to_async produce
+    pub fn encoded_len(&self) -> u64 {
+        self.pack.encoded_len()
+            + self.extra.iter().map(Element::encoded_len).sum::<u64>()
+            + self
+                .readers
+                .iter()
+                .map(|(name, len, _)| element_header_len(name) + 4 + *len as u64)
+                .sum::<u64>()
+    }
+
+    // Everything but the readers can be checked before anything is sent
+    pub(crate) fn check_value_types(&self) -> Result<(), PackError> {
+        check_value_types(self.pack.iter().chain(&self.extra))
+    }
+}
+
+impl<R: Read> PackStream<'_, R> {
+    // WriteTo stream everything into w
+    pub fn write_to<W: Write>(self, w: &mut W) -> Result<(), PackError> {
+        w.write_all(&len_u32(self.get_num())?.to_be_bytes())?;
+        for e in self.pack.iter().chain(&self.extra) {
+            e.write(w)?;
+        }
+        for (name, len, reader) in self.readers {
+            write_reader_header(w, &name, len)?;
+            let n = io::copy(&mut reader.take(len as u64), w).map_err(|e| PackError::io(e).in_element(&name))?;
+            check_copied(&name, n, len)?;
+        }
+        Ok(())
+    }
+}
+
+impl<R: AsyncRead + Unpin> PackStream<'_, R> {
+    // WriteToAsync stream everything into w
+    pub async fn write_to_async<W: AsyncWrite + Unpin>(self, w: &mut W) -> Result<(), PackError> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&len_u32(self.get_num())?.to_be_bytes());
+        write_elements_async(w, &mut buf, self.pack.elements()).await?;
+        write_elements_async(w, &mut buf, &self.extra).await?;
+        for (name, len, reader) in self.readers {
+            write_reader_header(&mut buf, &name, len)?;
+            w.write_all(&buf).await?;
+            buf.clear();
+            let n = tokio::io::copy(&mut reader.take(len as u64), w)
+                .await
+                .map_err(|e| PackError::io(e).in_element(&name))?;
+            check_copied(&name, n, len)?;
+        }
+        w.write_all(&buf).await?;
+        Ok(())
+    }
+}
+
+fn write_reader_header<W: Write>(w: &mut W, name: &str, len: u32) -> Result<(), PackError> {
+    write_element_header(w, name, ValueType::Data, 1)?;
+    w.write_all(&len.to_be_bytes())?;
+    Ok(())
+}
+
+// A reader that ends early would leave the stream shorter than announced
+fn check_copied(name: &str, n: u64, len: u32) -> Result<(), PackError> {
+    if n != len as u64 {
+        let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+        return Err(PackError::io(eof).in_element(name));
+    }
+    Ok(())
+}
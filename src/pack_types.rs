@@ -1,13 +1,43 @@
 // pack_types.rs - Exact translation of pack_types.go
 
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt;
 use std::error::Error;
+use std::io;
+use std::sync::Arc;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 // Constants - exact same as Go
 pub const MAX_VALUE_SIZE: u32 = 384 * 1024 * 1024; // 384MB
 pub const MAX_VALUE_NUM: u32 = 65536;
 pub const MAX_ELEMENT_NUM: u32 = 65536;
 pub const MAX_PACK_SIZE: u32 = 512 * 1024 * 1024; // 512MB
+pub const MAX_ELEMENT_NAME_LEN: u32 = 63;
+
+// PackLimits - resource limits applied while reading untrusted packs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackLimits {
+    pub max_total_size: u64,        // bytes consumed by the whole pack
+    pub max_elements: u32,
+    pub max_values_per_element: u32,
+    pub max_name_len: u32,          // bytes, excluding the terminator
+    pub max_value_size: u32,        // bytes of a single Data/Str/UniStr value
+}
+
+impl Default for PackLimits {
+    // The protocol maximums, as enforced by SoftEther itself
+    fn default() -> Self {
+        Self {
+            max_total_size: MAX_PACK_SIZE as u64,
+            max_elements: MAX_ELEMENT_NUM,
+            max_values_per_element: MAX_VALUE_NUM,
+            max_name_len: MAX_ELEMENT_NAME_LEN,
+            max_value_size: MAX_VALUE_SIZE,
+        }
+    }
+}
 
 // ValueType - exact same values as Go
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -33,31 +63,180 @@ impl ValueType {
     }
 }
 
-// PackError - exact same error types as Go
-#[derive(Debug, Clone)]
-pub enum PackError {
+// PackErrorKind - exact same error types as Go, plus Utf8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackErrorKind {
     NumberExceeds,
     SizeOver,
     InvalidType,
     SameNameExists,
     ZeroNumValue,
+    Utf8,
+    InvalidName,
     IoError,
 }
 
-impl fmt::Display for PackError {
+impl fmt::Display for PackErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            PackError::NumberExceeds => write!(f, "Number exceeds"),
-            PackError::SizeOver => write!(f, "Size over"),
-            PackError::InvalidType => write!(f, "Invalid type"),
-            PackError::SameNameExists => write!(f, "Same name exists"),
-            PackError::ZeroNumValue => write!(f, "Zero num value"),
-            PackError::IoError => write!(f, "IO error"),
+            PackErrorKind::NumberExceeds => write!(f, "Number exceeds"),
+            PackErrorKind::SizeOver => write!(f, "Size over"),
+            PackErrorKind::InvalidType => write!(f, "Invalid type"),
+            PackErrorKind::SameNameExists => write!(f, "Same name exists"),
+            PackErrorKind::ZeroNumValue => write!(f, "Zero num value"),
+            PackErrorKind::Utf8 => write!(f, "Invalid UTF-8"),
+            PackErrorKind::InvalidName => write!(f, "Invalid element name"),
+            PackErrorKind::IoError => write!(f, "IO error"),
+        }
+    }
+}
+
+// PackError - what went wrong, and where: the element being read or written,
+// the byte offset into the stream and, for unknown types, the raw type code
+#[derive(Debug, Clone)]
+pub struct PackError {
+    pub kind: PackErrorKind,
+    pub element: Option<String>,
+    pub offset: Option<u64>,
+    pub type_code: Option<u32>,
+    source: Option<Arc<dyn Error + Send + Sync>>,
+}
+
+impl PackError {
+    pub fn new(kind: PackErrorKind) -> Self {
+        Self {
+            kind,
+            element: None,
+            offset: None,
+            type_code: None,
+            source: None,
+        }
+    }
+
+    pub(crate) fn io(e: io::Error) -> Self {
+        Self {
+            source: Some(Arc::new(e)),
+            ..Self::new(PackErrorKind::IoError)
+        }
+    }
+
+    pub(crate) fn utf8(e: std::str::Utf8Error) -> Self {
+        Self {
+            source: Some(Arc::new(e)),
+            ..Self::new(PackErrorKind::Utf8)
+        }
+    }
+
+    pub(crate) fn invalid_type(code: u32) -> Self {
+        Self {
+            type_code: Some(code),
+            ..Self::new(PackErrorKind::InvalidType)
+        }
+    }
+
+    // Offset is only set once, by the innermost reader that saw the failure
+    pub(crate) fn at(mut self, offset: u64) -> Self {
+        self.offset.get_or_insert(offset);
+        self
+    }
+
+    pub(crate) fn in_element(mut self, name: &str) -> Self {
+        if self.element.is_none() {
+            self.element = Some(name.to_string());
+        }
+        self
+    }
+
+    // IoErrorKind - the kind of the underlying io::Error, if any
+    pub fn io_error_kind(&self) -> Option<io::ErrorKind> {
+        self.source.as_ref()?.downcast_ref::<io::Error>().map(io::Error::kind)
+    }
+}
+
+impl From<PackErrorKind> for PackError {
+    fn from(kind: PackErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(code) = self.type_code {
+            write!(f, " (type code {})", code)?;
+        }
+        if let Some(name) = &self.element {
+            write!(f, " in element '{}'", name)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {}", offset)?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, ": {}", source)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for PackError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|e| e as &(dyn Error + 'static))
+    }
+}
+
+impl From<io::Error> for PackError {
+    fn from(e: io::Error) -> Self {
+        PackError::io(e)
+    }
+}
+
+// PackFieldErrorKind - why a typed lookup failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PackFieldErrorKind {
+    Missing,
+    WrongType { expected: ValueType, found: ValueType },
+    IndexOutOfRange { index: u32, len: u32 },
+}
+
+// PackFieldError - a typed lookup failure, with the element name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackFieldError {
+    pub name: String,
+    pub kind: PackFieldErrorKind,
+}
+
+impl PackFieldError {
+    fn new(name: &str, kind: PackFieldErrorKind) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+        }
+    }
+
+    fn wrong_type(name: &str, expected: ValueType, found: ValueType) -> Self {
+        Self::new(name, PackFieldErrorKind::WrongType { expected, found })
+    }
+}
+
+impl fmt::Display for PackFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            PackFieldErrorKind::Missing => write!(f, "Element '{}' is missing", self.name),
+            PackFieldErrorKind::WrongType { expected, found } => write!(
+                f,
+                "Element '{}' has type {:?}, expected {:?}",
+                self.name, found, expected
+            ),
+            PackFieldErrorKind::IndexOutOfRange { index, len } => write!(
+                f,
+                "Element '{}' has {} values, index {} is out of range",
+                self.name, len, index
+            ),
         }
     }
 }
 
-impl Error for PackError {}
+impl Error for PackFieldError {}
 
 // Value - exact same as Go (enum, not struct!)
 #[derive(Debug, Clone)]
@@ -69,8 +248,22 @@ pub enum Value {
     Int64(u64),
 }
 
+impl Value {
+    // ValueType of this value
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Value::Int(_) => ValueType::Int,
+            Value::Data(_) => ValueType::Data,
+            Value::Str(_) => ValueType::Str,
+            Value::UniStr(_) => ValueType::UniStr,
+            Value::Int64(_) => ValueType::Int64,
+        }
+    }
+}
+
 // Element - exact same structure as Go
-#[derive(Debug, Clone)]
+// Debug is in pack_log.rs: secret values are redacted
+#[derive(Clone)]
 pub struct Element {
     pub name: String,
     pub type_: ValueType,    // Go field: Type
@@ -121,6 +314,24 @@ impl Element {
         }
     }
 
+    // GetInt64Value - exact same as Go
+    pub fn get_int64_value(&self, index: u32) -> u64 {
+        if let Some(Value::Int64(val)) = self.values.get(index as usize) {
+            *val
+        } else {
+            0
+        }
+    }
+
+    // GetUniStrValue - exact same as Go
+    pub fn get_uni_str_value(&self, index: u32) -> String {
+        if let Some(Value::UniStr(val)) = self.values.get(index as usize) {
+            val.clone()
+        } else {
+            String::new()
+        }
+    }
+
     // GetDataValue - exact same as Go
     pub fn get_data_value(&self, index: u32) -> Vec<u8> {
         if let Some(Value::Data(val)) = self.values.get(index as usize) {
@@ -129,52 +340,337 @@ impl Element {
             Vec::new()
         }
     }
+
+    // TryValue - the value at index, or why it isn't there
+    pub fn try_value(&self, index: u32) -> Result<&Value, PackFieldError> {
+        self.values.get(index as usize).ok_or_else(|| {
+            PackFieldError::new(
+                &self.name,
+                PackFieldErrorKind::IndexOutOfRange {
+                    index,
+                    len: self.values.len() as u32,
+                },
+            )
+        })
+    }
+
+    // Borrowed typed accessors - None if the index is out of range or the type differs
+    pub fn int_value(&self, index: u32) -> Option<u32> {
+        match self.values.get(index as usize) {
+            Some(Value::Int(val)) => Some(*val),
+            _ => None,
+        }
+    }
+
+    pub fn int64_value(&self, index: u32) -> Option<u64> {
+        match self.values.get(index as usize) {
+            Some(Value::Int64(val)) => Some(*val),
+            _ => None,
+        }
+    }
+
+    pub fn str_value(&self, index: u32) -> Option<&str> {
+        match self.values.get(index as usize) {
+            Some(Value::Str(val)) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn uni_str_value(&self, index: u32) -> Option<&str> {
+        match self.values.get(index as usize) {
+            Some(Value::UniStr(val)) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn data_value(&self, index: u32) -> Option<&[u8]> {
+        match self.values.get(index as usize) {
+            Some(Value::Data(val)) => Some(val),
+            _ => None,
+        }
+    }
+}
+
+// SystemToUINT64 - SoftEther's 64-bit time is milliseconds since the Unix
+// epoch (UTC); times before the epoch clamp to 0
+pub fn system_time_to_time64(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis().min(u64::MAX as u128) as u64)
+        .unwrap_or(0)
+}
+
+// UINT64ToSystem - inverse of system_time_to_time64; None if t is beyond
+// what SystemTime can represent on this platform (t comes off the wire)
+pub fn time64_to_system_time(t: u64) -> Option<SystemTime> {
+    UNIX_EPOCH.checked_add(Duration::from_millis(t))
+}
+
+// Case-insensitive name comparison; same result as comparing to_uppercase()
+// of both names, without allocating
+pub(crate) fn names_equal(a: &str, b: &str) -> bool {
+    a.chars()
+        .flat_map(char::to_uppercase)
+        .eq(b.chars().flat_map(char::to_uppercase))
+}
+
+impl<'a> IntoIterator for &'a Pack {
+    type Item = &'a Element;
+    type IntoIter = std::slice::Iter<'a, Element>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.iter()
+    }
+}
+
+// PackRow - one index across a set of parallel array elements
+#[derive(Debug, Clone, Copy)]
+pub struct PackRow<'a> {
+    pack: &'a Pack,
+    index: u32,
+}
+
+impl<'a> PackRow<'a> {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn get_int(&self, name: &str) -> u32 {
+        self.pack.get_int_ex(name, self.index)
+    }
+
+    pub fn get_int64(&self, name: &str) -> u64 {
+        self.pack.get_int64_ex(name, self.index)
+    }
+
+    pub fn get_bool(&self, name: &str) -> bool {
+        self.pack.get_bool_ex(name, self.index)
+    }
+
+    pub fn get_time64(&self, name: &str) -> Option<SystemTime> {
+        self.pack.get_time64_ex(name, self.index)
+    }
+
+    pub fn get_str(&self, name: &str) -> String {
+        self.pack.get_str_ex(name, self.index)
+    }
+
+    pub fn get_uni_str(&self, name: &str) -> String {
+        self.pack.get_uni_str_ex(name, self.index)
+    }
+
+    pub fn get_data(&self, name: &str) -> Vec<u8> {
+        self.pack.get_data_ex(name, self.index)
+    }
+
+    pub fn get_ip_addr(&self, name: &str) -> Option<IpAddr> {
+        self.pack.get_ip_addr_ex(name, self.index)
+    }
+}
+
+// ConflictPolicy - what Pack::merge does with an element name present in both packs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    KeepExisting,
+    Overwrite,
+    Error,
+}
+
+// Key of the name index: the uppercased name, which is what get_element compares
+fn fold_name(name: &str) -> Cow<'_, str> {
+    if !name.is_ascii() {
+        Cow::Owned(name.to_uppercase())
+    } else if name.bytes().any(|b| b.is_ascii_lowercase()) {
+        Cow::Owned(name.to_ascii_uppercase())
+    } else {
+        Cow::Borrowed(name)
+    }
 }
 
 // Pack - exact same structure as Go
-#[derive(Debug, Clone)]
+// Debug is in pack_log.rs: secret values are redacted
+#[derive(Clone)]
 pub struct Pack {
-    pub elements: Vec<Element>,
+    // Insertion order is the wire order. Elements must only be added through
+    // add_element so that `index` stays in sync; names are never changed in place.
+    pub(crate) elements: Vec<Element>,
+    // Folded name -> positions in `elements`, ascending
+    index: HashMap<String, Vec<usize>>,
+    // Group name given to elements added by the add_*_ex builders
+    pub(crate) current_json_hint_group_name: String,
+    // Every group name ever set, so empty groups still appear in JSON
+    pub(crate) json_subitem_names: Vec<String>,
 }
 
 impl Pack {
     pub fn new() -> Self {
         Self {
             elements: Vec::new(),
+            index: HashMap::new(),
+            current_json_hint_group_name: String::new(),
+            json_subitem_names: Vec::new(),
         }
     }
 
+    // SetCurrentJsonGroupName - an empty name ends the current group
+    pub fn set_current_json_group_name(&mut self, name: &str) {
+        self.current_json_hint_group_name = name.to_string();
+        if !name.is_empty() && !self.json_subitem_names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+            self.json_subitem_names.push(name.to_string());
+        }
+    }
+
+    pub fn current_json_group_name(&self) -> &str {
+        &self.current_json_hint_group_name
+    }
+
+    pub fn json_subitem_names(&self) -> &[String] {
+        &self.json_subitem_names
+    }
+
+    // GetIndexCount - number of values of the named element, 0 if missing
+    pub fn get_index_count(&self, name: &str) -> u32 {
+        self.get_element(name, None).map_or(0, |e| e.num_value() as u32)
+    }
+
+    // Rows - one row per value of key_name, for walking parallel arrays
+    pub fn rows(&self, key_name: &str) -> impl Iterator<Item = PackRow<'_>> {
+        (0..self.get_index_count(key_name)).map(move |index| PackRow { pack: self, index })
+    }
+
+    // GroupRows - one row per value of the elements in a JSON group
+    pub fn group_rows(&self, group: &str) -> impl Iterator<Item = PackRow<'_>> {
+        let count = self
+            .elements
+            .iter()
+            .find(|e| e.json_hint_is_array && e.json_hint_group_name.eq_ignore_ascii_case(group))
+            .map_or(0, |e| e.num_value() as u32);
+        (0..count).map(move |index| PackRow { pack: self, index })
+    }
+
     // AddElement - exact same logic as Go
     pub fn add_element(&mut self, e: Element) -> Result<(), PackError> {
         if e.values.is_empty() {
-            return Err(PackError::ZeroNumValue);
+            return Err(PackError::new(PackErrorKind::ZeroNumValue).in_element(&e.name));
         }
 
-        // Check for duplicate names - exact same as Go
-        for existing in &self.elements {
-            if existing.name == e.name {
-                return Err(PackError::SameNameExists);
-            }
+        // Duplicate names are checked case-sensitively - exact same as Go
+        let positions = self.index.entry(fold_name(&e.name).into_owned()).or_default();
+        if positions.iter().any(|&i| self.elements[i].name == e.name) {
+            return Err(PackError::new(PackErrorKind::SameNameExists).in_element(&e.name));
         }
 
+        positions.push(self.elements.len());
         self.elements.push(e);
         Ok(())
     }
 
+    // Remove - delete the element named name, preferring an exact-case match
+    pub fn remove(&mut self, name: &str) -> Option<Element> {
+        let i = self.find_target(name)?;
+        let e = self.elements.remove(i);
+        self.reindex();
+        Some(e)
+    }
+
+    // Replace - put e in place of the element of the same name (keeping its
+    // position), or append it; returns the element that was replaced
+    pub fn replace(&mut self, e: Element) -> Result<Option<Element>, PackError> {
+        self.replace_at(e).map(|(_, old)| old)
+    }
+
+    pub(crate) fn replace_at(&mut self, e: Element) -> Result<(usize, Option<Element>), PackError> {
+        if e.values.is_empty() {
+            return Err(PackError::new(PackErrorKind::ZeroNumValue).in_element(&e.name));
+        }
+        // Names that match case-insensitively share an index key, so the index is unchanged
+        match self.find_target(&e.name) {
+            Some(i) => Ok((i, Some(std::mem::replace(&mut self.elements[i], e)))),
+            None => {
+                self.add_element(e)?;
+                Ok((self.elements.len() - 1, None))
+            }
+        }
+    }
+
+    // Merge - move the elements of other into this pack; elements with the
+    // exact same name conflict, as they would in add_element
+    pub fn merge(&mut self, other: Pack, policy: ConflictPolicy) -> Result<(), PackError> {
+        if policy == ConflictPolicy::Error {
+            if let Some(e) = other.iter().find(|e| self.exact_position(&e.name).is_some()) {
+                return Err(PackError::new(PackErrorKind::SameNameExists).in_element(&e.name));
+            }
+        }
+
+        for e in other.elements {
+            match self.exact_position(&e.name) {
+                Some(i) if policy == ConflictPolicy::Overwrite => self.elements[i] = e,
+                Some(_) => {}
+                None => self.add_element(e)?,
+            }
+        }
+        for name in other.json_subitem_names {
+            if !self.json_subitem_names.iter().any(|n| n.eq_ignore_ascii_case(&name)) {
+                self.json_subitem_names.push(name);
+            }
+        }
+        Ok(())
+    }
+
+    // Retain - keep only the elements for which f returns true
+    pub fn retain<F: FnMut(&Element) -> bool>(&mut self, f: F) {
+        self.elements.retain(f);
+        self.reindex();
+    }
+
+    fn exact_position(&self, name: &str) -> Option<usize> {
+        self.index
+            .get(fold_name(name).as_ref())?
+            .iter()
+            .copied()
+            .find(|&i| self.elements[i].name == name)
+    }
+
+    // The exact-case element if there is one, else the first case-insensitive match
+    fn find_target(&self, name: &str) -> Option<usize> {
+        self.exact_position(name).or_else(|| self.position(name, None))
+    }
+
+    fn reindex(&mut self) {
+        self.index.clear();
+        for (i, e) in self.elements.iter().enumerate() {
+            self.index.entry(fold_name(&e.name).into_owned()).or_default().push(i);
+        }
+    }
+
+    // Elements in insertion (wire) order
+    pub fn elements(&self) -> &[Element] {
+        &self.elements
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Element> {
+        self.elements.iter()
+    }
+
+    pub fn into_elements(self) -> Vec<Element> {
+        self.elements
+    }
+
     // GetNum - exact same as Go
     pub fn get_num(&self) -> usize {
         self.elements.len()
     }
 
-    // GetElement - exact same logic as Go (case-insensitive)
+    // GetElement - exact same logic as Go (case-insensitive, first match wins)
     pub fn get_element(&self, name: &str, t: Option<ValueType>) -> Option<&Element> {
-        let n = name.to_uppercase();
-        for e in &self.elements {
-            if n == e.name.to_uppercase() && (t.is_none() || t == Some(e.type_)) {
-                return Some(e);
-            }
-        }
-        None
+        self.position(name, t).map(|i| &self.elements[i])
+    }
+
+    pub(crate) fn position(&self, name: &str, t: Option<ValueType>) -> Option<usize> {
+        self.index
+            .get(fold_name(name).as_ref())?
+            .iter()
+            .copied()
+            .find(|&i| t.is_none() || t == Some(self.elements[i].type_))
     }
 
     // GetInt - exact same as Go
@@ -191,6 +687,33 @@ impl Pack {
         }
     }
 
+    // GetInt64 - exact same as Go
+    pub fn get_int64(&self, name: &str) -> u64 {
+        self.get_int64_ex(name, 0)
+    }
+
+    // GetInt64Ex - exact same as Go
+    pub fn get_int64_ex(&self, name: &str, index: u32) -> u64 {
+        if let Some(e) = self.get_element(name, Some(ValueType::Int64)) {
+            e.get_int64_value(index)
+        } else {
+            0
+        }
+    }
+
+    // GetTime64 - date-time value; None if missing or zero (SoftEther's "not set")
+    pub fn get_time64(&self, name: &str) -> Option<SystemTime> {
+        self.get_time64_ex(name, 0)
+    }
+
+    // GetTime64Ex - date-time value at specific index; None also if out of range
+    pub fn get_time64_ex(&self, name: &str, index: u32) -> Option<SystemTime> {
+        match self.get_int64_ex(name, index) {
+            0 => None,
+            t => time64_to_system_time(t),
+        }
+    }
+
     // GetStr - exact same as Go
     pub fn get_str(&self, name: &str) -> String {
         self.get_str_ex(name, 0)
@@ -205,6 +728,20 @@ impl Pack {
         }
     }
 
+    // GetUniStr - exact same as Go
+    pub fn get_uni_str(&self, name: &str) -> String {
+        self.get_uni_str_ex(name, 0)
+    }
+
+    // GetUniStrEx - exact same as Go
+    pub fn get_uni_str_ex(&self, name: &str, index: u32) -> String {
+        if let Some(e) = self.get_element(name, Some(ValueType::UniStr)) {
+            e.get_uni_str_value(index)
+        } else {
+            String::new()
+        }
+    }
+
     // GetData - exact same as Go
     pub fn get_data(&self, name: &str) -> Vec<u8> {
         self.get_data_ex(name, 0)
@@ -229,6 +766,86 @@ impl Pack {
         self.get_int_ex(name, index) != 0
     }
 
+    // TryGetElement - like GetElement, but tells a missing element from one of another type
+    pub fn try_get_element(&self, name: &str, t: ValueType) -> Result<&Element, PackFieldError> {
+        if let Some(e) = self.get_element(name, Some(t)) {
+            return Ok(e);
+        }
+        match self.get_element(name, None) {
+            Some(e) => Err(PackFieldError::wrong_type(name, t, e.type_)),
+            None => Err(PackFieldError::new(name, PackFieldErrorKind::Missing)),
+        }
+    }
+
+    // TryGetInt - fallible GetInt
+    pub fn try_get_int(&self, name: &str) -> Result<u32, PackFieldError> {
+        self.try_get_int_ex(name, 0)
+    }
+
+    pub fn try_get_int_ex(&self, name: &str, index: u32) -> Result<u32, PackFieldError> {
+        match self.try_get_element(name, ValueType::Int)?.try_value(index)? {
+            Value::Int(val) => Ok(*val),
+            v => Err(PackFieldError::wrong_type(name, ValueType::Int, v.value_type())),
+        }
+    }
+
+    // TryGetInt64 - fallible GetInt64
+    pub fn try_get_int64(&self, name: &str) -> Result<u64, PackFieldError> {
+        self.try_get_int64_ex(name, 0)
+    }
+
+    pub fn try_get_int64_ex(&self, name: &str, index: u32) -> Result<u64, PackFieldError> {
+        match self.try_get_element(name, ValueType::Int64)?.try_value(index)? {
+            Value::Int64(val) => Ok(*val),
+            v => Err(PackFieldError::wrong_type(name, ValueType::Int64, v.value_type())),
+        }
+    }
+
+    // TryGetBool - fallible GetBool
+    pub fn try_get_bool(&self, name: &str) -> Result<bool, PackFieldError> {
+        self.try_get_bool_ex(name, 0)
+    }
+
+    pub fn try_get_bool_ex(&self, name: &str, index: u32) -> Result<bool, PackFieldError> {
+        Ok(self.try_get_int_ex(name, index)? != 0)
+    }
+
+    // TryGetStr - fallible GetStr, borrowing the value
+    pub fn try_get_str(&self, name: &str) -> Result<&str, PackFieldError> {
+        self.try_get_str_ex(name, 0)
+    }
+
+    pub fn try_get_str_ex(&self, name: &str, index: u32) -> Result<&str, PackFieldError> {
+        match self.try_get_element(name, ValueType::Str)?.try_value(index)? {
+            Value::Str(val) => Ok(val),
+            v => Err(PackFieldError::wrong_type(name, ValueType::Str, v.value_type())),
+        }
+    }
+
+    // TryGetUniStr - fallible GetUniStr, borrowing the value
+    pub fn try_get_uni_str(&self, name: &str) -> Result<&str, PackFieldError> {
+        self.try_get_uni_str_ex(name, 0)
+    }
+
+    pub fn try_get_uni_str_ex(&self, name: &str, index: u32) -> Result<&str, PackFieldError> {
+        match self.try_get_element(name, ValueType::UniStr)?.try_value(index)? {
+            Value::UniStr(val) => Ok(val),
+            v => Err(PackFieldError::wrong_type(name, ValueType::UniStr, v.value_type())),
+        }
+    }
+
+    // TryGetData - fallible GetData, borrowing the value
+    pub fn try_get_data(&self, name: &str) -> Result<&[u8], PackFieldError> {
+        self.try_get_data_ex(name, 0)
+    }
+
+    pub fn try_get_data_ex(&self, name: &str, index: u32) -> Result<&[u8], PackFieldError> {
+        match self.try_get_element(name, ValueType::Data)?.try_value(index)? {
+            Value::Data(val) => Ok(val),
+            v => Err(PackFieldError::wrong_type(name, ValueType::Data, v.value_type())),
+        }
+    }
+
     // GetArray - returns all values in an element as a vector
     pub fn get_array(&self, name: &str) -> Option<Vec<Value>> {
         if let Some(element) = self.get_element(name, None) {
@@ -248,6 +865,41 @@ impl Pack {
         self.get_data_ex(name, index).len() as u32
     }
 
+    // GetIpAddr - get IPv4 or IPv6 address (PackGetIp, honoring the @ipv6_* triplet)
+    pub fn get_ip_addr(&self, name: &str) -> Option<IpAddr> {
+        self.get_ip_addr_ex(name, 0)
+    }
+
+    // GetIpAddrEx - get IPv4 or IPv6 address at specific index
+    pub fn get_ip_addr_ex(&self, name: &str, index: u32) -> Option<IpAddr> {
+        self.get_ip_addr_with_scope_ex(name, index).map(|(ip, _)| ip)
+    }
+
+    // GetIpAddrWithScope - address plus its IPv6 scope id (0 for IPv4)
+    pub fn get_ip_addr_with_scope(&self, name: &str) -> Option<(IpAddr, u32)> {
+        self.get_ip_addr_with_scope_ex(name, 0)
+    }
+
+    // GetIpAddrWithScopeEx - address plus its IPv6 scope id at specific index
+    pub fn get_ip_addr_with_scope_ex(&self, name: &str, index: u32) -> Option<(IpAddr, u32)> {
+        if self.get_bool_ex(&format!("{}@ipv6_bool", name), index) {
+            // A short or missing array is zero-padded, as PackGetDataEx2 does
+            let mut octets = [0u8; 16];
+            if let Some(data) = self
+                .get_element(&format!("{}@ipv6_array", name), Some(ValueType::Data))
+                .and_then(|e| e.data_value(index))
+            {
+                let n = data.len().min(16);
+                octets[..n].copy_from_slice(&data[..n]);
+            }
+            let scope_id = self.get_int_ex(&format!("{}@ipv6_scope_id", name), index);
+            Some((IpAddr::V6(Ipv6Addr::from(octets)), scope_id))
+        } else {
+            let ip = self.get_element(name, Some(ValueType::Int))?.int_value(index)?;
+            Some((IpAddr::V4(Ipv4Addr::from(ip)), 0))
+        }
+    }
+
     // GetIp - get IPv4 address from int value (matches SoftEther stable PackGetIp)
     pub fn get_ip(&self, name: &str) -> String {
         self.get_ip_ex(name, 0)
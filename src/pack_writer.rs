@@ -3,6 +3,8 @@
 use crate::pack_types::*;
 use crate::memory::write_buf_str;
 use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::SystemTime;
 
 impl Pack {
     // AddStr add string value
@@ -23,11 +25,31 @@ impl Pack {
         self.elements.last()
     }
 
+    // AddUniStr add unicode string value
+    pub fn add_uni_str(&mut self, name: &str, str_val: &str) -> Option<&Element> {
+        let e = Element {
+            name: name.to_string(),
+            type_: ValueType::UniStr,
+            values: vec![Value::UniStr(str_val.to_string())],
+            json_hint_is_array: false,
+            json_hint_is_bool: false,
+            json_hint_is_date_time: false,
+            json_hint_is_ip: false,
+            json_hint_group_name: String::new(),
+        };
+        if self.add_element(e).is_err() {
+            return None;
+        }
+        self.elements.last()
+    }
+
     // AddBool add bool (as integer)
     pub fn add_bool(&mut self, name: &str, b: bool) -> Option<&Element> {
         let v = if b { 1u32 } else { 0u32 };
-        self.add_int(name, v)
-        // Note: JsonHint_IsBool not implemented yet
+        self.add_int(name, v)?;
+        let e = self.elements.last_mut()?;
+        e.json_hint_is_bool = true;
+        Some(e)
     }
 
     // AddInt add integer value
@@ -48,6 +70,32 @@ impl Pack {
         self.elements.last()
     }
 
+    // AddInt64 add 64-bit integer value
+    pub fn add_int64(&mut self, name: &str, i: u64) -> Option<&Element> {
+        let e = Element {
+            name: name.to_string(),
+            type_: ValueType::Int64,
+            values: vec![Value::Int64(i)],
+            json_hint_is_array: false,
+            json_hint_is_bool: false,
+            json_hint_is_date_time: false,
+            json_hint_is_ip: false,
+            json_hint_group_name: String::new(),
+        };
+        if self.add_element(e).is_err() {
+            return None;
+        }
+        self.elements.last()
+    }
+
+    // AddTime64 add date-time (as 64-bit milliseconds since the Unix epoch)
+    pub fn add_time64(&mut self, name: &str, t: SystemTime) -> Option<&Element> {
+        self.add_int64(name, system_time_to_time64(t))?;
+        let e = self.elements.last_mut()?;
+        e.json_hint_is_date_time = true;
+        Some(e)
+    }
+
     // AddData add data value
     pub fn add_data(&mut self, name: &str, data: Vec<u8>) -> Option<&Element> {
         let e = Element {
@@ -68,13 +116,236 @@ impl Pack {
 
     // AddIp32 add ipv4 (matches SoftEther stable PackAddIp)
     pub fn add_ip32(&mut self, name: &str, ip: u32) -> Option<&Element> {
-        // Note: JsonHint_IsIP not implemented yet
-        self.add_bool(&format!("{}@ipv6_bool", name), false);
-        self.add_data(&format!("{}@ipv6_array", name), vec![0u8; 16]);
-        self.add_int(&format!("{}@ipv6_scope_id", name), 0);
-
         // Store IP in network byte order (big-endian) to match SoftEther stable
-        self.add_int(name, ip)
+        self.add_ip_addr(name, IpAddr::V4(Ipv4Addr::from(ip)))
+    }
+
+    // AddIpAddr add IPv4 or IPv6 address (PackAddIp)
+    pub fn add_ip_addr(&mut self, name: &str, ip: IpAddr) -> Option<&Element> {
+        self.add_ip_addr_with_scope(name, ip, 0)
+    }
+
+    // AddIpAddrWithScope add IPv4 or IPv6 address; scope_id only applies to IPv6
+    pub fn add_ip_addr_with_scope(&mut self, name: &str, ip: IpAddr, scope_id: u32) -> Option<&Element> {
+        let (is_v6, array, scope_id, v4) = match ip {
+            IpAddr::V4(v4) => (false, [0u8; 16], 0, u32::from(v4)),
+            IpAddr::V6(v6) => (true, v6.octets(), scope_id, 0),
+        };
+
+        self.add_bool(&format!("{}@ipv6_bool", name), is_v6)?;
+        self.add_data(&format!("{}@ipv6_array", name), array.to_vec())?;
+        self.add_int(&format!("{}@ipv6_scope_id", name), scope_id)?;
+        self.add_int(name, v4)?;
+
+        // All four elements of the triplet carry JsonHint_IsIP
+        let n = self.elements.len();
+        for e in &mut self.elements[n - 4..] {
+            e.json_hint_is_ip = true;
+        }
+        self.elements.last()
+    }
+
+    // SetStr set string value, replacing any element of the same name
+    pub fn set_str(&mut self, name: &str, str_val: &str) -> Option<&Element> {
+        self.set_value(name, Value::Str(str_val.to_string())).map(|e| &*e)
+    }
+
+    // SetUniStr set unicode string value
+    pub fn set_uni_str(&mut self, name: &str, str_val: &str) -> Option<&Element> {
+        self.set_value(name, Value::UniStr(str_val.to_string())).map(|e| &*e)
+    }
+
+    // SetBool set bool (as integer)
+    pub fn set_bool(&mut self, name: &str, b: bool) -> Option<&Element> {
+        let e = self.set_value(name, Value::Int(b as u32))?;
+        e.json_hint_is_bool = true;
+        Some(e)
+    }
+
+    // SetInt set integer value
+    pub fn set_int(&mut self, name: &str, i: u32) -> Option<&Element> {
+        self.set_value(name, Value::Int(i)).map(|e| &*e)
+    }
+
+    // SetInt64 set 64-bit integer value
+    pub fn set_int64(&mut self, name: &str, i: u64) -> Option<&Element> {
+        self.set_value(name, Value::Int64(i)).map(|e| &*e)
+    }
+
+    // SetTime64 set date-time
+    pub fn set_time64(&mut self, name: &str, t: SystemTime) -> Option<&Element> {
+        let e = self.set_value(name, Value::Int64(system_time_to_time64(t)))?;
+        e.json_hint_is_date_time = true;
+        Some(e)
+    }
+
+    // SetData set data value
+    pub fn set_data(&mut self, name: &str, data: Vec<u8>) -> Option<&Element> {
+        self.set_value(name, Value::Data(data)).map(|e| &*e)
+    }
+
+    // SetIpAddr set IPv4 or IPv6 address, replacing the whole triplet
+    pub fn set_ip_addr(&mut self, name: &str, ip: IpAddr) -> Option<&Element> {
+        self.set_ip_addr_with_scope(name, ip, 0)
+    }
+
+    // SetIpAddrWithScope set IPv4 or IPv6 address with scope id
+    pub fn set_ip_addr_with_scope(&mut self, name: &str, ip: IpAddr, scope_id: u32) -> Option<&Element> {
+        self.remove_ip_addr(name);
+        self.add_ip_addr_with_scope(name, ip, scope_id)
+    }
+
+    // RemoveIpAddr remove the four elements of an IP triplet; true if any existed
+    pub fn remove_ip_addr(&mut self, name: &str) -> bool {
+        let mut removed = self.remove(name).is_some();
+        for suffix in ["@ipv6_bool", "@ipv6_array", "@ipv6_scope_id"] {
+            removed |= self.remove(&format!("{}{}", name, suffix)).is_some();
+        }
+        removed
+    }
+
+    fn set_value(&mut self, name: &str, value: Value) -> Option<&mut Element> {
+        let mut e = Element::new(name.to_string(), value.value_type());
+        e.values.push(value);
+        let (i, _) = self.replace_at(e).ok()?;
+        Some(&mut self.elements[i])
+    }
+
+    // AddIntEx add value at index of an array element holding total values
+    pub fn add_int_ex(&mut self, name: &str, i: u32, index: u32, total: u32) -> Option<&Element> {
+        self.add_array_value(name, Value::Int(i), index, total)
+    }
+
+    // AddInt64Ex add 64-bit value at index of an array element
+    pub fn add_int64_ex(&mut self, name: &str, i: u64, index: u32, total: u32) -> Option<&Element> {
+        self.add_array_value(name, Value::Int64(i), index, total)
+    }
+
+    // AddBoolEx add bool at index of an array element
+    pub fn add_bool_ex(&mut self, name: &str, b: bool, index: u32, total: u32) -> Option<&Element> {
+        let group = self.current_json_hint_group_name.clone();
+        let e = self.add_value_ex(name, Value::Int(b as u32), index, total, Some(&group)).ok()?;
+        e.json_hint_is_bool = true;
+        Some(e)
+    }
+
+    // AddTime64Ex add date-time at index of an array element
+    pub fn add_time64_ex(&mut self, name: &str, t: SystemTime, index: u32, total: u32) -> Option<&Element> {
+        let group = self.current_json_hint_group_name.clone();
+        let v = Value::Int64(system_time_to_time64(t));
+        let e = self.add_value_ex(name, v, index, total, Some(&group)).ok()?;
+        e.json_hint_is_date_time = true;
+        Some(e)
+    }
+
+    // AddStrEx add string at index of an array element
+    pub fn add_str_ex(&mut self, name: &str, str_val: &str, index: u32, total: u32) -> Option<&Element> {
+        self.add_array_value(name, Value::Str(str_val.to_string()), index, total)
+    }
+
+    // AddUniStrEx add unicode string at index of an array element
+    pub fn add_uni_str_ex(&mut self, name: &str, str_val: &str, index: u32, total: u32) -> Option<&Element> {
+        self.add_array_value(name, Value::UniStr(str_val.to_string()), index, total)
+    }
+
+    // AddDataEx add data at index of an array element
+    pub fn add_data_ex(&mut self, name: &str, data: Vec<u8>, index: u32, total: u32) -> Option<&Element> {
+        self.add_array_value(name, Value::Data(data), index, total)
+    }
+
+    // AddIpAddrEx add IPv4 or IPv6 address at index of the four triplet arrays
+    pub fn add_ip_addr_ex(&mut self, name: &str, ip: IpAddr, index: u32, total: u32) -> Option<&Element> {
+        self.add_ip_addr_with_scope_ex(name, ip, 0, index, total)
+    }
+
+    // AddIpAddrWithScopeEx add IPv4 or IPv6 address with scope id at index
+    pub fn add_ip_addr_with_scope_ex(
+        &mut self,
+        name: &str,
+        ip: IpAddr,
+        scope_id: u32,
+        index: u32,
+        total: u32,
+    ) -> Option<&Element> {
+        let group = self.current_json_hint_group_name.clone();
+        self.add_ip_value_ex(name, ip, scope_id, index, total, Some(&group))
+            .ok()
+            .map(|e| &*e)
+    }
+
+    fn add_array_value(&mut self, name: &str, value: Value, index: u32, total: u32) -> Option<&Element> {
+        let group = self.current_json_hint_group_name.clone();
+        self.add_value_ex(name, value, index, total, Some(&group))
+            .ok()
+            .map(|e| &*e)
+    }
+
+    // Set values[index] of an element holding `total` values, creating the element
+    // on first use (PackAdd*Ex); group is None for single values
+    pub(crate) fn add_value_ex(
+        &mut self,
+        name: &str,
+        value: Value,
+        index: u32,
+        total: u32,
+        group: Option<&str>,
+    ) -> Result<&mut Element, PackError> {
+        if index >= total {
+            return Err(PackError::new(PackErrorKind::NumberExceeds).in_element(name));
+        }
+        let type_ = value.value_type();
+
+        let pos = match self.position(name, Some(type_)) {
+            Some(pos) => pos,
+            None => {
+                let mut e = Element::new(name.to_string(), type_);
+                e.values = (0..total).map(|_| default_value(type_)).collect();
+                self.add_element(e)?;
+                self.elements.len() - 1
+            }
+        };
+
+        let e = &mut self.elements[pos];
+        // A later call may announce a larger total than the one that created it
+        if e.values.len() < total as usize {
+            e.values.resize_with(total as usize, || default_value(type_));
+        }
+        e.values[index as usize] = value;
+        if let Some(group) = group {
+            e.json_hint_is_array = true;
+            e.json_hint_group_name = group.to_string();
+        }
+        Ok(e)
+    }
+
+    // PackAddIpEx2 - the four elements of the IP triplet; returns the IPv4 element
+    pub(crate) fn add_ip_value_ex(
+        &mut self,
+        name: &str,
+        ip: IpAddr,
+        scope_id: u32,
+        index: u32,
+        total: u32,
+        group: Option<&str>,
+    ) -> Result<&mut Element, PackError> {
+        let (is_v6, array, scope_id, v4) = match ip {
+            IpAddr::V4(v4) => (false, [0u8; 16], 0, u32::from(v4)),
+            IpAddr::V6(v6) => (true, v6.octets(), scope_id, 0),
+        };
+
+        let bool_name = format!("{}@ipv6_bool", name);
+        let e = self.add_value_ex(&bool_name, Value::Int(is_v6 as u32), index, total, group)?;
+        e.json_hint_is_bool = true;
+        e.json_hint_is_ip = true;
+        let array_name = format!("{}@ipv6_array", name);
+        self.add_value_ex(&array_name, Value::Data(array.to_vec()), index, total, group)?
+            .json_hint_is_ip = true;
+        let scope_name = format!("{}@ipv6_scope_id", name);
+        self.add_value_ex(&scope_name, Value::Int(scope_id), index, total, group)?
+            .json_hint_is_ip = true;
+        let e = self.add_value_ex(name, Value::Int(v4), index, total, group)?;
+        e.json_hint_is_ip = true;
+        Ok(e)
     }
 
     // AddIp add IPv4 from string (helper method)
@@ -94,25 +365,135 @@ impl Pack {
 
     // ToBuf To buffer
     pub fn to_buf(&self) -> Result<Vec<u8>, PackError> {
-        let mut buf = Vec::new();
+        self.to_buf_with(&WriteOptions::default())
+    }
+
+    // ToBufWith To buffer, optionally validating first and sorting elements
+    pub fn to_buf_with(&self, opts: &WriteOptions) -> Result<Vec<u8>, PackError> {
+        if opts.strict {
+            self.validate()?;
+        }
+
+        let mut elements: Vec<&Element> = self.elements.iter().collect();
+        if opts.canonical {
+            // Names are unique byte-wise, so this is a total order
+            elements.sort_by(|a, b| a.name.as_bytes().cmp(b.name.as_bytes()));
+        }
+
+        let mut buf = Vec::with_capacity(usize::try_from(self.encoded_len()).unwrap_or(0));
         
-        buf.extend_from_slice(&(self.elements.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&len_u32(elements.len())?.to_be_bytes());
         
-        for e in &self.elements {
+        for e in elements {
             e.write(&mut buf)?;
         }
         
         Ok(buf)
     }
+
+    // EncodedLen size in bytes of what to_buf / write_to produce
+    pub fn encoded_len(&self) -> u64 {
+        4 + self.elements.iter().map(Element::encoded_len).sum::<u64>()
+    }
+
+    // WriteTo stream the pack into w without building it in memory first
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), PackError> {
+        w.write_all(&len_u32(self.elements.len())?.to_be_bytes())?;
+        for e in &self.elements {
+            e.write(w)?;
+        }
+        Ok(())
+    }
+
+    // Validate check the pack against the limits SoftEther enforces when sending
+    pub fn validate(&self) -> Result<(), PackError> {
+        if self.elements.len() > MAX_ELEMENT_NUM as usize {
+            return Err(PackError::new(PackErrorKind::NumberExceeds));
+        }
+        for e in &self.elements {
+            e.validate().map_err(|err| err.in_element(&e.name))?;
+        }
+        // Checked up front so an oversized pack is never serialized
+        if self.encoded_len() > MAX_PACK_SIZE as u64 {
+            return Err(PackError::new(PackErrorKind::SizeOver));
+        }
+        Ok(())
+    }
+}
+
+// WriteOptions - how Pack::to_buf_with serializes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    // Reject packs a SoftEther peer would refuse: see Pack::validate
+    pub strict: bool,
+    // Sort elements by name so equal packs serialize to identical bytes
+    pub canonical: bool,
+}
+
+// Lengths are sent as u32; anything larger can't be represented
+pub(crate) fn len_u32(n: usize) -> Result<u32, PackError> {
+    u32::try_from(n).map_err(|_| PackError::new(PackErrorKind::SizeOver))
+}
+
+// The only way serializing can fail part way through, short of an IO error,
+// is a value whose variant disagrees with its element's type. Streaming
+// writers check this before they send a length they couldn't deliver.
+pub(crate) fn check_value_types<'a>(elements: impl IntoIterator<Item = &'a Element>) -> Result<(), PackError> {
+    for e in elements {
+        if e.values.iter().any(|v| v.value_type() != e.type_) {
+            return Err(PackError::new(PackErrorKind::InvalidType).in_element(&e.name));
+        }
+    }
+    Ok(())
+}
+
+// Element names are ASCII identifiers such as "session_key" or "ip@ipv6_bool"
+fn is_valid_element_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| b.is_ascii_graphic())
 }
 
 impl Element {
+    // Validate name, value count, value sizes and that every value matches type_
+    pub fn validate(&self) -> Result<(), PackError> {
+        if self.name.len() > MAX_ELEMENT_NAME_LEN as usize {
+            return Err(PackError::new(PackErrorKind::SizeOver));
+        }
+        if !is_valid_element_name(&self.name) {
+            return Err(PackError::new(PackErrorKind::InvalidName));
+        }
+        if self.values.is_empty() {
+            return Err(PackError::new(PackErrorKind::ZeroNumValue));
+        }
+        if self.values.len() > MAX_VALUE_NUM as usize {
+            return Err(PackError::new(PackErrorKind::NumberExceeds));
+        }
+        for v in &self.values {
+            if v.value_type() != self.type_ {
+                return Err(PackError::new(PackErrorKind::InvalidType));
+            }
+            let size = match v {
+                Value::Int(_) | Value::Int64(_) => 0,
+                Value::Data(d) => d.len(),
+                Value::Str(s) | Value::UniStr(s) => s.len() + 1,
+            };
+            if size > MAX_VALUE_SIZE as usize {
+                return Err(PackError::new(PackErrorKind::SizeOver));
+            }
+        }
+        Ok(())
+    }
+
+    // EncodedLen size in bytes of the serialized element
+    pub fn encoded_len(&self) -> u64 {
+        element_header_len(&self.name) + self.values.iter().map(Value::encoded_len).sum::<u64>()
+    }
+
     pub fn write<W: Write>(&self, w: &mut W) -> Result<(), PackError> {
-        write_buf_str(w, &self.name).map_err(|_| PackError::IoError)?;
-        
-        w.write_all(&(self.type_ as u32).to_be_bytes()).map_err(|_| PackError::IoError)?;
-        
-        w.write_all(&(self.num_value() as u32).to_be_bytes()).map_err(|_| PackError::IoError)?;
+        self.write_inner(w).map_err(|e| e.in_element(&self.name))
+    }
+
+    fn write_inner<W: Write>(&self, w: &mut W) -> Result<(), PackError> {
+        write_element_header(w, &self.name, self.type_, self.num_value())?;
         
         for v in &self.values {
             v.write(w, self.type_)?;
@@ -122,33 +503,79 @@ impl Element {
     }
 }
 
+// Name, type and value count, as sent before the values
+pub(crate) fn write_element_header<W: Write>(
+    w: &mut W,
+    name: &str,
+    t: ValueType,
+    num_value: usize,
+) -> Result<(), PackError> {
+    write_buf_str(w, name)?;
+    w.write_all(&(t as u32).to_be_bytes())?;
+    w.write_all(&len_u32(num_value)?.to_be_bytes())?;
+    Ok(())
+}
+
+pub(crate) fn element_header_len(name: &str) -> u64 {
+    4 + name.len() as u64 + 4 + 4
+}
+
 impl Value {
+    // EncodedLen size in bytes of the serialized value
+    pub fn encoded_len(&self) -> u64 {
+        match self {
+            Value::Int(_) => 4,
+            Value::Int64(_) => 8,
+            Value::Data(d) => 4 + d.len() as u64,
+            Value::Str(s) => 4 + s.len() as u64,
+            // Up to the first NUL, plus the terminator
+            Value::UniStr(s) => 4 + s.bytes().position(|c| c == 0).unwrap_or(s.len()) as u64 + 1,
+        }
+    }
+
     pub fn write<W: Write>(&self, w: &mut W, t: ValueType) -> Result<(), PackError> {
         match (self, t) {
             (Value::Int(val), ValueType::Int) => {
-                w.write_all(&val.to_be_bytes()).map_err(|_| PackError::IoError)?;
+                w.write_all(&val.to_be_bytes())?;
             }
             (Value::Int64(val), ValueType::Int64) => {
-                w.write_all(&val.to_be_bytes()).map_err(|_| PackError::IoError)?;
+                w.write_all(&val.to_be_bytes())?;
             }
             (Value::Data(data), ValueType::Data) => {
-                let s = data.len() as i32;
-                w.write_all(&s.to_be_bytes()).map_err(|_| PackError::IoError)?;
-                w.write_all(data).map_err(|_| PackError::IoError)?;
+                let s = len_u32(data.len())?;
+                w.write_all(&s.to_be_bytes())?;
+                w.write_all(data)?;
             }
             (Value::Str(string), ValueType::Str) => {
                 let b = string.as_bytes();
-                let s = b.len() as u32;
-                w.write_all(&s.to_be_bytes()).map_err(|_| PackError::IoError)?;
-                w.write_all(b).map_err(|_| PackError::IoError)?;
+                let s = len_u32(b.len())?;
+                w.write_all(&s.to_be_bytes())?;
+                w.write_all(b)?;
             }
-            (Value::UniStr(_), ValueType::UniStr) => {
-                panic!("unimplemented");
+            (Value::UniStr(string), ValueType::UniStr) => {
+                // UTF-8 bytes up to the first NUL, followed by the NUL itself;
+                // the size includes the terminator, so "" is written as size 1
+                let b = string.as_bytes();
+                let b = &b[..b.iter().position(|&c| c == 0).unwrap_or(b.len())];
+                let s = len_u32(b.len())?.checked_add(1).ok_or(PackErrorKind::SizeOver)?;
+                w.write_all(&s.to_be_bytes())?;
+                w.write_all(b)?;
+                w.write_all(&[0u8])?;
             }
             _ => {
-                return Err(PackError::InvalidType);
+                return Err(PackError::new(PackErrorKind::InvalidType));
             }
         }
         Ok(())
     }
 }
+
+fn default_value(t: ValueType) -> Value {
+    match t {
+        ValueType::Int => Value::Int(0),
+        ValueType::Int64 => Value::Int64(0),
+        ValueType::Data => Value::Data(Vec::new()),
+        ValueType::Str => Value::Str(String::new()),
+        ValueType::UniStr => Value::UniStr(String::new()),
+    }
+}
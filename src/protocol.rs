@@ -0,0 +1,682 @@
+// protocol.rs - Typed SoftEther handshake messages
+//
+// Each message maps to the pack SoftEther sends (Protocol.c), with the same
+// element names. from_pack checks that required fields are present with the
+// right type and size; to_pack writes them back in the same layout.
+
+use crate::encrypt::{secure_password, Sha1Sum, SHA1_SIZE};
+use crate::http::read_http_pack;
+use crate::mayaqua::{ErrServerIsNotVpn, ERR_SERVER_IS_NOT_VPN};
+use crate::pack_log::Secret;
+use crate::pack_types::*;
+use std::error::Error;
+use std::fmt;
+use std::net::IpAddr;
+use tracing::debug;
+
+// Client authentication types sent in "authtype"
+pub const CLIENT_AUTHTYPE_ANONYMOUS: u32 = 0;
+pub const CLIENT_AUTHTYPE_PASSWORD: u32 = 1;
+pub const CLIENT_AUTHTYPE_PLAIN_PASSWORD: u32 = 2;
+pub const CLIENT_AUTHTYPE_CERT: u32 = 3;
+pub const AUTHTYPE_TICKET: u32 = 99;
+
+// Underlying transport sent in "protocol"
+pub const CONNECTION_TCP: u32 = 0;
+pub const CONNECTION_UDP: u32 = 1;
+
+// Client identity sent by default (SoftEther VPN 4.43 Build 9799)
+pub const CLIENT_STR: &str = "SoftEther VPN Client";
+pub const CLIENT_VER: u32 = 443;
+pub const CLIENT_BUILD: u32 = 9799;
+
+// Session limits the C client applies to the welcome pack
+pub const MAX_TCP_CONNECTION: u32 = 32;
+pub const TIMEOUT_MIN: u32 = 5 * 1000;
+pub const TIMEOUT_MAX: u32 = 60 * 1000;
+pub const TIMEOUT_DEFAULT: u32 = 30 * 1000;
+
+// UDP acceleration key sizes, by protocol version
+pub const UDP_ACCELERATION_COMMON_KEY_SIZE_V1: usize = 20;
+pub const UDP_ACCELERATION_COMMON_KEY_SIZE_V2: usize = 128;
+
+// Prefix of the policy elements in the welcome pack, e.g. "policy:MaxConnection"
+pub const POLICY_PREFIX: &str = "policy:";
+
+// First server build with UDP acceleration v2 (ChaCha20-Poly1305), 4.34 Build 9744
+pub const UDP_ACCEL_V2_MIN_BUILD: u32 = 9744;
+
+// ProtocolError - why a handshake pack was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+    // A required element is missing or has the wrong type
+    Field(PackFieldError),
+    // An element is present but its value is unusable
+    InvalidValue { name: String, reason: String },
+    // The peer replied with a non-zero "error" code
+    Server(u32),
+    // The peer did not answer with a SoftEther hello (web server, proxy page, ...)
+    NotVpn(ErrServerIsNotVpn),
+}
+
+impl ProtocolError {
+    fn invalid(name: &str, reason: impl Into<String>) -> Self {
+        ProtocolError::InvalidValue {
+            name: name.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolError::Field(e) => write!(f, "{}", e),
+            ProtocolError::InvalidValue { name, reason } => {
+                write!(f, "Element '{}' is invalid: {}", name, reason)
+            }
+            ProtocolError::Server(code) => write!(f, "Server returned error {}", code),
+            ProtocolError::NotVpn(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ProtocolError {}
+
+impl From<PackFieldError> for ProtocolError {
+    fn from(e: PackFieldError) -> Self {
+        ProtocolError::Field(e)
+    }
+}
+
+// GetErrorFromPack - a reply carrying a non-zero "error" is a failure
+pub fn check_error(p: &Pack) -> Result<(), ProtocolError> {
+    match p.get_int("error") {
+        0 => Ok(()),
+        code => Err(ProtocolError::Server(code)),
+    }
+}
+
+// A Data element that must be exactly SHA1_SIZE bytes
+fn get_sha1(p: &Pack, name: &str) -> Result<Sha1Sum, ProtocolError> {
+    let data = p.try_get_data(name)?;
+    data.try_into()
+        .map_err(|_| ProtocolError::invalid(name, format!("expected {} bytes, got {}", SHA1_SIZE, data.len())))
+}
+
+fn check_method(p: &Pack, method: &str) -> Result<(), ProtocolError> {
+    let m = p.try_get_str("method")?;
+    if !m.eq_ignore_ascii_case(method) {
+        return Err(ProtocolError::invalid("method", format!("expected \"{}\", got \"{}\"", method, m)));
+    }
+    Ok(())
+}
+
+// ServerHello - the first pack the server sends (hello, version, build, random)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerHello {
+    pub hello: String,
+    pub version: u32,
+    pub build: u32,
+    // Challenge the client signs or hashes its password with
+    pub random: Sha1Sum,
+    // Any other Int elements newer builds send, in pack order
+    pub capabilities: Vec<(String, u32)>,
+}
+
+// Element names ServerHello reads into its own fields
+const HELLO_FIELDS: &[&str] = &["hello", "version", "build", "random"];
+
+impl ServerHello {
+    // Anything that isn't a complete hello means we aren't talking to a VPN
+    // server; the detail is only logged
+    pub fn from_pack(p: &Pack) -> Result<Self, ProtocolError> {
+        check_error(p)?;
+        Self::parse(p).map_err(|e| {
+            debug!(error = %e, "invalid server hello");
+            ProtocolError::NotVpn(ERR_SERVER_IS_NOT_VPN)
+        })
+    }
+
+    // Parse the body of the first HTTP response, which may be a web page
+    pub fn from_http_body(body: &[u8]) -> Result<Self, ProtocolError> {
+        if is_html(body) {
+            debug!(len = body.len(), "HTML instead of a server hello");
+            return Err(ProtocolError::NotVpn(ERR_SERVER_IS_NOT_VPN));
+        }
+        let p = read_http_pack(body).map_err(|e| {
+            debug!(error = %e, "server hello is not a pack");
+            ProtocolError::NotVpn(ERR_SERVER_IS_NOT_VPN)
+        })?;
+        Self::from_pack(&p)
+    }
+
+    fn parse(p: &Pack) -> Result<Self, ProtocolError> {
+        let capabilities = p
+            .iter()
+            .filter(|e| e.type_ == ValueType::Int && !HELLO_FIELDS.iter().any(|f| f.eq_ignore_ascii_case(&e.name)))
+            .map(|e| (e.name.clone(), e.get_int_value(0)))
+            .collect();
+        Ok(Self {
+            hello: p.try_get_str("hello")?.to_string(),
+            version: p.try_get_int("version")?,
+            build: p.try_get_int("build")?,
+            random: get_sha1(p, "random")?,
+            capabilities,
+        })
+    }
+
+    pub fn to_pack(&self) -> Pack {
+        let mut p = Pack::new();
+        p.add_str("hello", &self.hello);
+        p.add_int("version", self.version);
+        p.add_int("build", self.build);
+        p.add_data("random", self.random.to_vec());
+        for (name, value) in &self.capabilities {
+            p.add_int(name, *value);
+        }
+        p
+    }
+
+    // Capability - an extra flag sent in the hello, if present
+    pub fn capability(&self, name: &str) -> Option<u32> {
+        self.capabilities.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|&(_, v)| v)
+    }
+
+    pub fn is_build_at_least(&self, build: u32) -> bool {
+        self.build >= build
+    }
+
+    pub fn supports_udp_accel_v2(&self) -> bool {
+        self.is_build_at_least(UDP_ACCEL_V2_MIN_BUILD)
+    }
+}
+
+// A web server or captive portal answers with a page instead of a pack
+fn is_html(body: &[u8]) -> bool {
+    let start = body.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(body.len());
+    let head = &body[start..body.len().min(start + 16)];
+    [&b"<!doctype"[..], b"<html", b"<head", b"<?xml"]
+        .iter()
+        .any(|tag| head.len() >= tag.len() && head[..tag.len()].eq_ignore_ascii_case(tag))
+}
+
+// AuthData - the credential part of a login pack, one variant per authtype;
+// Debug hides the credentials
+#[derive(Clone, PartialEq, Eq)]
+pub enum AuthData {
+    Anonymous,
+    // SHA-0 of the password hash and the server random
+    Password { secure_password: Sha1Sum },
+    // Sent as is, for RADIUS / NT domain authentication
+    PlainPassword { plain_password: String },
+    // DER certificate and the signature of the server random
+    Cert { cert: Vec<u8>, sign: Vec<u8> },
+    Ticket { ticket: Sha1Sum },
+}
+
+impl AuthData {
+    pub fn authtype(&self) -> u32 {
+        match self {
+            AuthData::Anonymous => CLIENT_AUTHTYPE_ANONYMOUS,
+            AuthData::Password { .. } => CLIENT_AUTHTYPE_PASSWORD,
+            AuthData::PlainPassword { .. } => CLIENT_AUTHTYPE_PLAIN_PASSWORD,
+            AuthData::Cert { .. } => CLIENT_AUTHTYPE_CERT,
+            AuthData::Ticket { .. } => AUTHTYPE_TICKET,
+        }
+    }
+
+    fn add_to(&self, p: &mut Pack) {
+        match self {
+            AuthData::Anonymous => {}
+            AuthData::Password { secure_password } => {
+                p.add_data("secure_password", secure_password.to_vec());
+            }
+            AuthData::PlainPassword { plain_password } => {
+                p.add_str("plain_password", plain_password);
+            }
+            AuthData::Cert { cert, sign } => {
+                p.add_data("cert", cert.clone());
+                p.add_data("sign", sign.clone());
+            }
+            AuthData::Ticket { ticket } => {
+                p.add_data("ticket", ticket.to_vec());
+            }
+        }
+    }
+
+    fn from_pack(p: &Pack) -> Result<Self, ProtocolError> {
+        match p.try_get_int("authtype")? {
+            CLIENT_AUTHTYPE_ANONYMOUS => Ok(AuthData::Anonymous),
+            CLIENT_AUTHTYPE_PASSWORD => Ok(AuthData::Password {
+                secure_password: get_sha1(p, "secure_password")?,
+            }),
+            CLIENT_AUTHTYPE_PLAIN_PASSWORD => Ok(AuthData::PlainPassword {
+                plain_password: p.try_get_str("plain_password")?.to_string(),
+            }),
+            CLIENT_AUTHTYPE_CERT => Ok(AuthData::Cert {
+                cert: p.try_get_data("cert")?.to_vec(),
+                sign: p.try_get_data("sign")?.to_vec(),
+            }),
+            AUTHTYPE_TICKET => Ok(AuthData::Ticket {
+                ticket: get_sha1(p, "ticket")?,
+            }),
+            other => Err(ProtocolError::invalid("authtype", format!("unknown auth type {}", other))),
+        }
+    }
+}
+
+impl fmt::Debug for AuthData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthData::Anonymous => f.write_str("Anonymous"),
+            AuthData::Password { secure_password } => f
+                .debug_struct("Password")
+                .field("secure_password", &Secret::bytes(secure_password))
+                .finish(),
+            AuthData::PlainPassword { plain_password } => f
+                .debug_struct("PlainPassword")
+                .field("plain_password", &Secret::chars(plain_password))
+                .finish(),
+            AuthData::Cert { cert, sign } => f
+                .debug_struct("Cert")
+                .field("cert", &format_args!("<{} bytes>", cert.len()))
+                .field("sign", &Secret::bytes(sign))
+                .finish(),
+            AuthData::Ticket { ticket } => f.debug_struct("Ticket").field("ticket", &Secret::bytes(ticket)).finish(),
+        }
+    }
+}
+
+// ClientAuth - the client's "login" pack (ClientUploadAuth)
+//
+// Not sent: the support_bulk_on_rudp / support_hmac_on_bulk_of_rudp /
+// support_udp_recovery flags and the client's UDP acceleration parameters,
+// which advertise transports this crate doesn't implement, and the node info
+// and Windows version block, which the server only uses for logging and
+// display. A login without them is accepted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientAuth {
+    pub hubname: String,
+    pub username: String,
+    pub auth: AuthData,
+    // Also sent as "hello", "version" and "build"
+    pub client_str: String,
+    pub client_ver: u32,
+    pub client_build: u32,
+    pub protocol: u32,
+    // Cedar ClientId; 0 for the standard client
+    pub client_id: u32,
+    pub max_connection: u32,
+    pub use_encrypt: bool,
+    pub use_compress: bool,
+    pub half_connection: bool,
+    pub require_bridge_routing_mode: bool,
+    pub require_monitor_mode: bool,
+    // false asks the server not to prioritise VoIP / QoS packets
+    pub qos: bool,
+    // A stable per-machine hash (GenerateMachineUniqueHash); zero if unknown
+    pub unique_id: Sha1Sum,
+}
+
+impl ClientAuth {
+    // A login with the default client identity over a single encrypted TCP
+    // connection; adjust the public fields before calling to_pack
+    pub fn new(hubname: &str, username: &str, auth: AuthData) -> Self {
+        Self {
+            hubname: hubname.to_string(),
+            username: username.to_string(),
+            auth,
+            client_str: CLIENT_STR.to_string(),
+            client_ver: CLIENT_VER,
+            client_build: CLIENT_BUILD,
+            protocol: CONNECTION_TCP,
+            client_id: 0,
+            max_connection: 1,
+            use_encrypt: true,
+            use_compress: false,
+            half_connection: false,
+            require_bridge_routing_mode: false,
+            require_monitor_mode: false,
+            qos: true,
+            unique_id: Sha1Sum::default(),
+        }
+    }
+
+    // PackLoginWithAnonymous
+    pub fn anonymous(hubname: &str, username: &str) -> Self {
+        Self::new(hubname, username, AuthData::Anonymous)
+    }
+
+    // PackLoginWithPassword - hashed_password is hash_password(username, password),
+    // random the challenge from the server hello
+    pub fn password(hubname: &str, username: &str, hashed_password: &Sha1Sum, random: &Sha1Sum) -> Self {
+        let secure_password = secure_password(hashed_password, random);
+        Self::new(hubname, username, AuthData::Password { secure_password })
+    }
+
+    // PackLoginWithPlainPassword - for RADIUS / NT domain authentication
+    pub fn plain_password(hubname: &str, username: &str, plain_password: &str) -> Self {
+        let plain_password = plain_password.to_string();
+        Self::new(hubname, username, AuthData::PlainPassword { plain_password })
+    }
+
+    // PackLoginWithCert - cert is the DER client certificate, sign the RSA
+    // signature of the server random made with its private key
+    pub fn cert(hubname: &str, username: &str, cert: Vec<u8>, sign: Vec<u8>) -> Self {
+        Self::new(hubname, username, AuthData::Cert { cert, sign })
+    }
+
+    // Login with the ticket issued when the server redirected the session
+    pub fn ticket(hubname: &str, username: &str, ticket: Sha1Sum) -> Self {
+        Self::new(hubname, username, AuthData::Ticket { ticket })
+    }
+
+    pub fn from_pack(p: &Pack) -> Result<Self, ProtocolError> {
+        check_method(p, "login")?;
+        Ok(Self {
+            hubname: p.try_get_str("hubname")?.to_string(),
+            username: p.try_get_str("username")?.to_string(),
+            auth: AuthData::from_pack(p)?,
+            client_str: p.get_str("client_str"),
+            client_ver: p.get_int("client_ver"),
+            client_build: p.get_int("client_build"),
+            protocol: p.get_int("protocol"),
+            client_id: p.get_int("client_id"),
+            max_connection: p.get_int("max_connection"),
+            use_encrypt: p.get_bool("use_encrypt"),
+            use_compress: p.get_bool("use_compress"),
+            half_connection: p.get_bool("half_connection"),
+            require_bridge_routing_mode: p.get_bool("require_bridge_routing_mode"),
+            require_monitor_mode: p.get_bool("require_monitor_mode"),
+            qos: p.get_bool("qos"),
+            // Older clients don't send it
+            unique_id: match p.get_element("unique_id", None) {
+                Some(_) => get_sha1(p, "unique_id")?,
+                None => Sha1Sum::default(),
+            },
+        })
+    }
+
+    pub fn to_pack(&self) -> Pack {
+        let mut p = Pack::new();
+        p.add_str("method", "login");
+        p.add_str("hubname", &self.hubname);
+        p.add_str("username", &self.username);
+        p.add_int("authtype", self.auth.authtype());
+        self.auth.add_to(&mut p);
+        p.add_str("client_str", &self.client_str);
+        p.add_int("client_ver", self.client_ver);
+        p.add_int("client_build", self.client_build);
+        p.add_int("protocol", self.protocol);
+        p.add_str("hello", &self.client_str);
+        p.add_int("version", self.client_ver);
+        p.add_int("build", self.client_build);
+        p.add_int("client_id", self.client_id);
+        p.add_int("max_connection", self.max_connection);
+        p.add_bool("use_encrypt", self.use_encrypt);
+        p.add_bool("use_compress", self.use_compress);
+        p.add_bool("half_connection", self.half_connection);
+        p.add_bool("require_bridge_routing_mode", self.require_bridge_routing_mode);
+        p.add_bool("require_monitor_mode", self.require_monitor_mode);
+        p.add_bool("qos", self.qos);
+        p.add_data("unique_id", self.unique_id.to_vec());
+        p
+    }
+}
+
+// Welcome - the server's reply to a successful login; Debug hides the keys
+#[derive(Clone, PartialEq, Eq)]
+pub struct Welcome {
+    pub session_name: String,
+    pub connection_name: String,
+    // 1..=MAX_TCP_CONNECTION
+    pub max_connection: u32,
+    pub use_encrypt: bool,
+    pub use_compress: bool,
+    pub half_connection: bool,
+    // Milliseconds, TIMEOUT_MIN..=TIMEOUT_MAX
+    pub timeout: u32,
+    pub qos: bool,
+    pub session_key: Sha1Sum,
+    pub session_key_32: u32,
+    // The "policy:*" elements without the prefix, in pack order
+    pub policies: Vec<(String, u32)>,
+    // None when the server didn't offer UDP acceleration, or offered it
+    // with parameters the client can't use
+    pub udp_accel: Option<UdpAccel>,
+}
+
+// UdpAccel - UDP acceleration parameters from the welcome pack
+#[derive(Clone, PartialEq, Eq)]
+pub struct UdpAccel {
+    // 1 or 2
+    pub version: u32,
+    pub server_ip: IpAddr,
+    pub server_port: u16,
+    // UDP_ACCELERATION_COMMON_KEY_SIZE_V1 or _V2 bytes, as per version
+    pub server_key: Vec<u8>,
+    pub server_cookie: u32,
+    pub client_cookie: u32,
+    pub use_encryption: bool,
+    pub use_hmac: bool,
+    pub fast_disconnect_detect: bool,
+}
+
+impl fmt::Debug for Welcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Welcome")
+            .field("session_name", &self.session_name)
+            .field("connection_name", &self.connection_name)
+            .field("max_connection", &self.max_connection)
+            .field("use_encrypt", &self.use_encrypt)
+            .field("use_compress", &self.use_compress)
+            .field("half_connection", &self.half_connection)
+            .field("timeout", &self.timeout)
+            .field("qos", &self.qos)
+            .field("session_key", &Secret::bytes(&self.session_key))
+            .field("session_key_32", &format_args!("<redacted>"))
+            .field("policies", &self.policies)
+            .field("udp_accel", &self.udp_accel)
+            .finish()
+    }
+}
+
+impl fmt::Debug for UdpAccel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UdpAccel")
+            .field("version", &self.version)
+            .field("server_ip", &self.server_ip)
+            .field("server_port", &self.server_port)
+            .field("server_key", &Secret::bytes(&self.server_key))
+            .field("server_cookie", &self.server_cookie)
+            .field("client_cookie", &self.client_cookie)
+            .field("use_encryption", &self.use_encryption)
+            .field("use_hmac", &self.use_hmac)
+            .field("fast_disconnect_detect", &self.fast_disconnect_detect)
+            .finish()
+    }
+}
+
+impl UdpAccel {
+    fn from_pack(p: &Pack) -> Result<Self, ProtocolError> {
+        let version = p.get_int("udp_acceleration_version").max(1);
+        let (key_name, key_size) = match version {
+            1 => ("udp_acceleration_server_key", UDP_ACCELERATION_COMMON_KEY_SIZE_V1),
+            2 => ("udp_acceleration_server_key_v2", UDP_ACCELERATION_COMMON_KEY_SIZE_V2),
+            v => return Err(ProtocolError::invalid("udp_acceleration_version", format!("unknown version {}", v))),
+        };
+
+        let server_ip = p
+            .get_ip_addr("udp_acceleration_server_ip")
+            .filter(|ip| !ip.is_unspecified())
+            .ok_or_else(|| ProtocolError::invalid("udp_acceleration_server_ip", "missing or zero"))?;
+        let server_port = match p.get_int("udp_acceleration_server_port") {
+            0 => return Err(ProtocolError::invalid("udp_acceleration_server_port", "zero")),
+            port => u16::try_from(port).map_err(|_| ProtocolError::invalid("udp_acceleration_server_port", "out of range"))?,
+        };
+        let server_key = p.try_get_data(key_name)?;
+        if server_key.len() != key_size {
+            return Err(ProtocolError::invalid(key_name, format!("expected {} bytes, got {}", key_size, server_key.len())));
+        }
+
+        Ok(Self {
+            version,
+            server_ip,
+            server_port,
+            server_key: server_key.to_vec(),
+            server_cookie: p.get_int("udp_acceleration_server_cookie"),
+            client_cookie: p.get_int("udp_acceleration_client_cookie"),
+            use_encryption: p.get_bool("udp_acceleration_use_encryption"),
+            use_hmac: p.get_bool("use_hmac_on_udp_acceleration"),
+            fast_disconnect_detect: p.get_bool("udp_accel_fast_disconnect_detect"),
+        })
+    }
+
+    fn add_to(&self, p: &mut Pack) {
+        p.add_bool("use_udp_acceleration", true);
+        p.add_int("udp_acceleration_version", self.version);
+        p.add_ip_addr("udp_acceleration_server_ip", self.server_ip);
+        p.add_int("udp_acceleration_server_port", self.server_port as u32);
+        let key_name = if self.version >= 2 { "udp_acceleration_server_key_v2" } else { "udp_acceleration_server_key" };
+        p.add_data(key_name, self.server_key.clone());
+        p.add_int("udp_acceleration_server_cookie", self.server_cookie);
+        p.add_int("udp_acceleration_client_cookie", self.client_cookie);
+        p.add_bool("udp_acceleration_use_encryption", self.use_encryption);
+        p.add_bool("use_hmac_on_udp_acceleration", self.use_hmac);
+        p.add_bool("udp_accel_fast_disconnect_detect", self.fast_disconnect_detect);
+    }
+}
+
+impl Welcome {
+    // Missing numbers fall back to what the C client uses, and out-of-range
+    // ones are clamped the same way (ClientConnect)
+    pub fn from_pack(p: &Pack) -> Result<Self, ProtocolError> {
+        check_error(p)?;
+
+        let timeout = match p.get_int("timeout") {
+            0 => TIMEOUT_DEFAULT,
+            t => t.clamp(TIMEOUT_MIN, TIMEOUT_MAX),
+        };
+        let policies = p
+            .iter()
+            .filter(|e| e.type_ == ValueType::Int)
+            .filter_map(|e| {
+                let name = e.name.get(..POLICY_PREFIX.len())?;
+                name.eq_ignore_ascii_case(POLICY_PREFIX)
+                    .then(|| (e.name[POLICY_PREFIX.len()..].to_string(), e.get_int_value(0)))
+            })
+            .collect();
+        // Unusable parameters only turn acceleration off, like the C client
+        let udp_accel = match p.get_bool("use_udp_acceleration") {
+            false => None,
+            true => UdpAccel::from_pack(p)
+                .map_err(|e| debug!(error = %e, "UDP acceleration disabled"))
+                .ok(),
+        };
+
+        Ok(Self {
+            session_name: p.try_get_str("session_name")?.to_string(),
+            connection_name: p.try_get_str("connection_name")?.to_string(),
+            max_connection: p.get_int("max_connection").clamp(1, MAX_TCP_CONNECTION),
+            use_encrypt: p.get_bool("use_encrypt"),
+            use_compress: p.get_bool("use_compress"),
+            half_connection: p.get_bool("half_connection"),
+            timeout,
+            qos: p.get_bool("qos"),
+            session_key: get_sha1(p, "session_key")?,
+            session_key_32: p.get_int("session_key_32"),
+            policies,
+            udp_accel,
+        })
+    }
+
+    pub fn to_pack(&self) -> Pack {
+        let mut p = Pack::new();
+        p.add_str("session_name", &self.session_name);
+        p.add_str("connection_name", &self.connection_name);
+        p.add_int("max_connection", self.max_connection);
+        p.add_bool("use_encrypt", self.use_encrypt);
+        p.add_bool("use_compress", self.use_compress);
+        p.add_bool("half_connection", self.half_connection);
+        p.add_int("timeout", self.timeout);
+        p.add_bool("qos", self.qos);
+        p.add_data("session_key", self.session_key.to_vec());
+        p.add_int("session_key_32", self.session_key_32);
+        for (name, value) in &self.policies {
+            p.add_int(&format!("{}{}", POLICY_PREFIX, name), *value);
+        }
+        if let Some(udp_accel) = &self.udp_accel {
+            udp_accel.add_to(&mut p);
+        }
+        p
+    }
+
+    // Policy - a "policy:*" value by name without the prefix, e.g. "MaxConnection"
+    pub fn policy(&self, name: &str) -> Option<u32> {
+        self.policies.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|&(_, v)| v)
+    }
+}
+
+// ErrorReply - a reply that carries only an error code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorReply {
+    pub error: u32,
+}
+
+impl ErrorReply {
+    pub fn from_pack(p: &Pack) -> Result<Self, ProtocolError> {
+        Ok(Self {
+            error: p.try_get_int("error")?,
+        })
+    }
+
+    pub fn to_pack(&self) -> Pack {
+        let mut p = Pack::new();
+        p.add_int("error", self.error);
+        p
+    }
+}
+
+// AdditionalConnect - request to add a TCP connection to an established
+// session; Debug hides the session key
+#[derive(Clone, PartialEq, Eq)]
+pub struct AdditionalConnect {
+    pub session_key: Sha1Sum,
+    pub client_str: String,
+    pub client_ver: u32,
+    pub client_build: u32,
+}
+
+impl fmt::Debug for AdditionalConnect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AdditionalConnect")
+            .field("session_key", &Secret::bytes(&self.session_key))
+            .field("client_str", &self.client_str)
+            .field("client_ver", &self.client_ver)
+            .field("client_build", &self.client_build)
+            .finish()
+    }
+}
+
+impl AdditionalConnect {
+    pub fn from_pack(p: &Pack) -> Result<Self, ProtocolError> {
+        check_method(p, "additional_connect")?;
+        Ok(Self {
+            session_key: get_sha1(p, "session_key")?,
+            client_str: p.get_str("client_str"),
+            client_ver: p.get_int("client_ver"),
+            client_build: p.get_int("client_build"),
+        })
+    }
+
+    pub fn to_pack(&self) -> Pack {
+        let mut p = Pack::new();
+        p.add_str("method", "additional_connect");
+        p.add_data("session_key", self.session_key.to_vec());
+        p.add_str("client_str", &self.client_str);
+        p.add_int("client_ver", self.client_ver);
+        p.add_int("client_build", self.client_build);
+        p
+    }
+}
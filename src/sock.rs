@@ -2,6 +2,8 @@
 
 use std::io;
 use tokio::net::TcpStream;
+use crate::pack_async::{read_pack_async, write_pack_async};
+use crate::pack_types::Pack;
 use tokio_rustls::{TlsStream, client::TlsStream as ClientTlsStream};
 
 // AsyncSock - Async version of Sock for SoftEther-rust  
@@ -108,6 +110,25 @@ impl AsyncSock {
     }
 }
 
+impl AsyncSock {
+    /// Send a size-prefixed pack (SendPack), for use after the HTTP phase
+    pub async fn send_pack(&mut self, p: &Pack) -> io::Result<()> {
+        tracing::trace!(pack = %p.redacted(), "send_pack");
+        write_pack_async(&mut self.tls_stream, p).await.map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Pack send failed: {}", e))
+        })
+    }
+
+    /// Receive a size-prefixed pack (RecvPack)
+    pub async fn recv_pack(&mut self) -> io::Result<Pack> {
+        let p = read_pack_async(&mut self.tls_stream).await.map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Pack receive failed: {}", e))
+        })?;
+        tracing::trace!(pack = %p.redacted(), "recv_pack");
+        Ok(p)
+    }
+}
+
 // TODO: Implement exact translation of sock.go
 pub struct SockStub;
 
@@ -0,0 +1,74 @@
+// tests/encrypt_test.rs - SHA-0 test vectors and streaming
+
+use mayaqua::*;
+
+fn hex(b: &[u8]) -> String {
+    b.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha0_published_vectors() {
+        assert_eq!(hex(&sha0(b"")), "f96cea198ad1dd5617ac084a3d92c6107708c0ef");
+        assert_eq!(hex(&sha0(b"abc")), "0164b8a914cd2a5e74c4f7ff082c4d97f1edf880");
+        assert_eq!(
+            hex(&sha0(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+            "d2516ee1acfa5baf33dfc1c471e438449ef134c8"
+        );
+        assert_eq!(hex(&sha0(&vec![b'a'; 1_000_000])), "3232affa48628a26653b5aaa44541fd90d690603");
+    }
+
+    #[test]
+    fn test_sha0_streaming_matches_one_shot() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+        let expected = sha0(&data);
+
+        // Piece sizes straddling the 64-byte block and the 56-byte padding boundary
+        for piece in [1, 3, 55, 56, 63, 64, 65, 200] {
+            let mut ctx = Sha0Context::new();
+            for chunk in data.chunks(piece) {
+                ctx.update(chunk);
+            }
+            assert_eq!(ctx.finalize(), expected, "piece size {}", piece);
+        }
+    }
+
+    #[test]
+    fn test_sha0_clone_keeps_midstate() {
+        let mut prefix = Sha0Context::default();
+        prefix.update(b"password");
+
+        let mut a = prefix.clone();
+        a.update(b"ALICE");
+        let mut b = prefix;
+        b.update(b"BOB");
+
+        assert_eq!(a.finalize(), sha0(b"passwordALICE"));
+        assert_eq!(b.finalize(), sha0(b"passwordBOB"));
+    }
+
+    #[test]
+    fn test_hash_password_and_secure_password() {
+        // Reference values from an independent SHA-0 implementation
+        let hashed = hash_password("alice", "secret");
+        assert_eq!(hex(&hashed), "0e782dacdb78918d73007999da81bc408906d451");
+        assert_eq!(hashed, sha0(b"secretALICE"));
+        // Only the username is case-insensitive
+        assert_eq!(hash_password("Alice", "secret"), hashed);
+        assert_ne!(hash_password("alice", "Secret"), hashed);
+
+        let random: Sha1Sum = std::array::from_fn(|i| i as u8);
+        let secure = secure_password(&hashed, &random);
+        assert_eq!(hex(&secure), "1394b82845bfb7a2711c4963f2b62978cb2c6ce3");
+
+        assert!(verify_secure_password(&hashed, &random, &secure));
+        let mut wrong = secure;
+        wrong[19] ^= 1;
+        assert!(!verify_secure_password(&hashed, &random, &wrong));
+        assert!(!verify_secure_password(&hashed, &random, &secure[..19]));
+        assert!(!verify_secure_password(&hashed, &[0u8; 20], &secure));
+    }
+}
@@ -0,0 +1,112 @@
+// tests/ffi_test.rs - C ABI (run with --features ffi)
+#![cfg(feature = "ffi")]
+
+use mayaqua::ffi::*;
+use mayaqua::*;
+use std::ffi::{c_char, CStr};
+use std::ptr;
+
+fn encode(p: *const Pack) -> Vec<u8> {
+    let (mut data, mut len) = (ptr::null_mut(), 0usize);
+    unsafe {
+        assert_eq!(mayaqua_pack_to_buf(p, &mut data, &mut len), MayaquaError::Ok);
+        let buf = std::slice::from_raw_parts(data, len).to_vec();
+        mayaqua_bytes_free(data, len);
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi_round_trip_matches_rust_encoding() {
+        unsafe {
+            let p = mayaqua_pack_new();
+            assert_eq!(mayaqua_pack_add_str(p, c"method".as_ptr(), c"login".as_ptr()), MayaquaError::Ok);
+            assert_eq!(mayaqua_pack_add_uni_str(p, c"hubname".as_ptr(), c"VPN\u{e9}".as_ptr()), MayaquaError::Ok);
+            assert_eq!(mayaqua_pack_add_int(p, c"max_connection".as_ptr(), 8), MayaquaError::Ok);
+            assert_eq!(mayaqua_pack_add_int64(p, c"time".as_ptr(), 1 << 40), MayaquaError::Ok);
+            assert_eq!(mayaqua_pack_add_bool(p, c"use_encrypt".as_ptr(), true), MayaquaError::Ok);
+            assert_eq!(mayaqua_pack_add_data(p, c"random".as_ptr(), [1u8, 2, 3].as_ptr(), 3), MayaquaError::Ok);
+            assert_eq!(mayaqua_pack_get_num(p), 6);
+
+            let mut expected = Pack::new();
+            expected.add_str("method", "login");
+            expected.add_uni_str("hubname", "VPN\u{e9}");
+            expected.add_int("max_connection", 8);
+            expected.add_int64("time", 1 << 40);
+            expected.add_bool("use_encrypt", true);
+            expected.add_data("random", vec![1, 2, 3]);
+            let buf = encode(p);
+            assert_eq!(buf, expected.to_buf().unwrap());
+            mayaqua_pack_free(p);
+
+            let mut q: *mut Pack = ptr::null_mut();
+            assert_eq!(mayaqua_read_pack(buf.as_ptr(), buf.len(), &mut q), MayaquaError::Ok);
+
+            let mut s: *mut c_char = ptr::null_mut();
+            assert_eq!(mayaqua_pack_get_uni_str(q, c"hubname".as_ptr(), &mut s), MayaquaError::Ok);
+            assert_eq!(CStr::from_ptr(s).to_str().unwrap(), "VPN\u{e9}");
+            mayaqua_string_free(s);
+
+            let (mut n, mut b, mut t) = (0u32, false, 0u64);
+            assert_eq!(mayaqua_pack_get_int(q, c"max_connection".as_ptr(), &mut n), MayaquaError::Ok);
+            assert_eq!(mayaqua_pack_get_bool(q, c"use_encrypt".as_ptr(), &mut b), MayaquaError::Ok);
+            assert_eq!(mayaqua_pack_get_int64(q, c"time".as_ptr(), &mut t), MayaquaError::Ok);
+            assert_eq!((n, b, t), (8, true, 1 << 40));
+
+            let (mut data, mut len) = (ptr::null_mut(), 0usize);
+            assert_eq!(mayaqua_pack_get_data(q, c"random".as_ptr(), &mut data, &mut len), MayaquaError::Ok);
+            assert_eq!(std::slice::from_raw_parts(data, len), &[1, 2, 3]);
+            mayaqua_bytes_free(data, len);
+            mayaqua_pack_free(q);
+        }
+    }
+
+    #[test]
+    fn test_ffi_error_codes() {
+        unsafe {
+            let p = mayaqua_pack_new();
+            assert_eq!(mayaqua_pack_add_int(p, c"n".as_ptr(), 1), MayaquaError::Ok);
+            assert_eq!(mayaqua_pack_add_int(p, c"n".as_ptr(), 2), MayaquaError::SameNameExists);
+            assert_eq!(mayaqua_pack_add_int(p, c"bad name".as_ptr(), 2), MayaquaError::InvalidName);
+            assert_eq!(mayaqua_pack_add_int(ptr::null_mut(), c"n".as_ptr(), 1), MayaquaError::NullPointer);
+            assert_eq!(mayaqua_pack_add_str(p, c"s".as_ptr(), c"\xff".as_ptr()), MayaquaError::InvalidString);
+
+            let mut out = 0u32;
+            assert_eq!(mayaqua_pack_get_int(p, c"missing".as_ptr(), &mut out), MayaquaError::NotFound);
+            let mut s: *mut c_char = ptr::null_mut();
+            assert_eq!(mayaqua_pack_get_str(p, c"n".as_ptr(), &mut s), MayaquaError::WrongType);
+            assert!(s.is_null());
+
+            let mut q: *mut Pack = ptr::null_mut();
+            assert_eq!(mayaqua_read_pack([0u8, 0].as_ptr(), 2, &mut q), MayaquaError::Io);
+            assert!(q.is_null());
+
+            let msg = |code| CStr::from_ptr(mayaqua_error_string(code)).to_str().unwrap();
+            assert_eq!(msg(MayaquaError::SameNameExists as u32), "Same name exists");
+            assert_eq!(msg(MayaquaError::Io as u32), "IO error");
+            assert_eq!(msg(MayaquaError::Io as u32 + 1), "Unknown error");
+            assert_eq!(msg(u32::MAX), "Unknown error");
+
+            // Freeing NULL is a no-op, like free()
+            mayaqua_pack_free(ptr::null_mut());
+            mayaqua_string_free(ptr::null_mut());
+            mayaqua_bytes_free(ptr::null_mut(), 0);
+            mayaqua_pack_free(p);
+        }
+    }
+
+    #[test]
+    fn test_committed_header_is_current() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/mayaqua.h"));
+        let committed = include_str!("../include/mayaqua.h");
+        assert!(
+            generated == committed,
+            "include/mayaqua.h is stale; regenerate it with\n  \
+             cbindgen --config cbindgen.toml --crate mayaqua --output include/mayaqua.h"
+        );
+    }
+}
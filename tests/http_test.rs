@@ -0,0 +1,96 @@
+// tests/http_test.rs - HTTP pack padding ("pencore")
+
+use mayaqua::http::{create_dummy_value, HTTP_PACK_PADDING_NAME};
+use mayaqua::*;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+
+// Yields n bytes of 0xAA, then fails
+struct FailAfter(usize);
+
+impl AsyncRead for FailAfter {
+    fn poll_read(mut self: Pin<&mut Self>, _: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        if self.0 == 0 {
+            return Poll::Ready(Err(io::Error::other("disk gone")));
+        }
+        let n = self.0.min(buf.remaining());
+        buf.put_slice(&vec![0xAA; n]);
+        self.0 -= n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dummy_value_size_distribution() {
+        let sizes: Vec<usize> = (0..200)
+            .map(|_| {
+                let e = create_dummy_value();
+                assert_eq!(e.name, HTTP_PACK_PADDING_NAME);
+                assert_eq!(e.type_, ValueType::Data);
+                e.get_data_value(0).len()
+            })
+            .collect();
+
+        assert!(sizes.iter().all(|&n| n < HTTP_PACK_RAND_SIZE_MAX as usize));
+        // Uniform over 0..1000, so 200 samples are essentially never all equal
+        assert!(sizes.iter().any(|&n| n != sizes[0]));
+    }
+
+    #[test]
+    fn test_read_http_pack_strips_padding() {
+        let mut pack = Pack::new();
+        pack.add_str("hello", "SoftEther VPN Server");
+        let mut stream = PackStream::<std::io::Empty>::new(&pack);
+        stream.add_element(create_dummy_value()).unwrap();
+        let mut body = Vec::new();
+        stream.write_to(&mut body).unwrap();
+
+        assert!(read_pack(&mut std::io::Cursor::new(&body)).unwrap().get_element("pencore", None).is_some());
+        let received = read_http_pack(&body).unwrap();
+        assert_eq!(received.get_num(), 1);
+        assert_eq!(received.get_str("hello"), "SoftEther VPN Server");
+
+        assert!(HttpSendOptions::default().pencore);
+    }
+
+    #[tokio::test]
+    async fn test_failed_body_closes_the_request_stream() {
+        let mut pack = Pack::new();
+        pack.add_str("method", "upload");
+        let mut stream = PackStream::new(&pack);
+        stream.add_data_reader("file", 1000, FailAfter(100)).unwrap();
+        let content_length = stream.encoded_len();
+
+        let (mut client, mut server) = tokio::io::duplex(1 << 16);
+        let e = write_http_request(&mut client, "203.0.113.7", stream).await.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(e.to_string().contains("disk gone"), "{}", e);
+
+        // The server sees the stream end, not a request still in progress
+        let mut received = Vec::new();
+        server.read_to_end(&mut received).await.unwrap();
+        let text = String::from_utf8_lossy(&received);
+        assert!(text.contains(&format!("Content-Length: {}\r\n", content_length)));
+        let body = received.len() - (text.find("\r\n\r\n").unwrap() + 4);
+        assert!((body as u64) < content_length);
+    }
+
+    #[tokio::test]
+    async fn test_unserializable_pack_sends_nothing() {
+        let mut mixed = Element::new("mixed".to_string(), ValueType::Int);
+        mixed.values = vec![Value::Int(1), Value::Str("x".into())];
+        let mut pack = Pack::new();
+        pack.add_element(mixed).unwrap();
+
+        let mut out = Vec::new();
+        let stream = PackStream::<tokio::io::Empty>::new(&pack);
+        assert!(write_http_request(&mut out, "203.0.113.7", stream).await.is_err());
+        assert!(out.is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+// tests/pack_async_test.rs - Async pack framing and codec
+
+use bytes::BytesMut;
+use mayaqua::*;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::AsyncWrite;
+use tokio_util::codec::{Decoder, Encoder};
+
+fn sample() -> Pack {
+    let mut pack = Pack::new();
+    pack.add_str("method", "login");
+    pack.add_int("max_connection", 8);
+    pack
+}
+
+// Accepts limit bytes, then fails every write; records whether it was shut down
+#[derive(Default)]
+struct FailingWriter {
+    written: Vec<u8>,
+    limit: usize,
+    shut_down: bool,
+}
+
+impl AsyncWrite for FailingWriter {
+    fn poll_write(mut self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let n = buf.len().min(self.limit - self.written.len());
+        if n == 0 {
+            return Poll::Ready(Err(io::Error::from(io::ErrorKind::BrokenPipe)));
+        }
+        self.written.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.shut_down = true;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_write_pack_async() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+
+        let writer = tokio::spawn(async move {
+            write_pack_async(&mut client, &sample()).await.unwrap();
+            write_pack_async(&mut client, &sample()).await.unwrap();
+        });
+
+        for _ in 0..2 {
+            let p = read_pack_async(&mut server).await.unwrap();
+            assert_eq!(p.get_str("method"), "login");
+            assert_eq!(p.get_int("max_connection"), 8);
+        }
+        writer.await.unwrap();
+    }
+
+    #[test]
+    fn test_codec_frames() {
+        let body = sample().to_buf().unwrap();
+        let mut codec = PackCodec::new();
+
+        let mut buf = BytesMut::new();
+        codec.encode(&sample(), &mut buf).unwrap();
+        assert_eq!(&buf[..4], &(body.len() as u32).to_be_bytes());
+        assert_eq!(&buf[4..], &body[..]);
+
+        // Partial frames wait for more data
+        let mut partial = BytesMut::from(&buf[..buf.len() - 1]);
+        assert!(codec.decode(&mut partial).unwrap().is_none());
+
+        buf.extend_from_slice(&buf.clone());
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap().get_str("method"), "login");
+        assert!(codec.decode(&mut buf).unwrap().is_some());
+        assert!(buf.is_empty());
+
+        let mut zero = BytesMut::from(&[0u8, 0, 0, 0][..]);
+        assert!(codec.decode(&mut zero).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_streaming_writes_match_to_buf() {
+        let mut pack = sample();
+        pack.add_data("blob", vec![0x5A; 100_000]);
+        pack.add_uni_str("msg", "héllo");
+        let expected = pack.to_buf().unwrap();
+        assert_eq!(pack.encoded_len(), expected.len() as u64);
+
+        let mut sync_out = Vec::new();
+        pack.write_to(&mut sync_out).unwrap();
+        assert_eq!(sync_out, expected);
+
+        let mut async_out = Vec::new();
+        pack.write_to_async(&mut async_out).await.unwrap();
+        assert_eq!(async_out, expected);
+    }
+
+    #[tokio::test]
+    async fn test_pack_stream_with_readers() {
+        let base = sample();
+        let payload = vec![7u8; 50_000];
+
+        let mut stream = PackStream::new(&base);
+        stream.add_data_reader("upload", payload.len() as u32, &payload[..]).unwrap();
+        assert!(stream.add_data_reader("method", 1, &payload[..1]).is_err());
+        let len = stream.encoded_len();
+
+        let mut out = Vec::new();
+        stream.write_to_async(&mut out).await.unwrap();
+        assert_eq!(out.len() as u64, len);
+
+        let p = read_pack(&mut std::io::Cursor::new(&out)).unwrap();
+        assert_eq!(p.get_str("method"), "login");
+        assert_eq!(p.get_data("upload"), payload);
+
+        // The sync writer produces the same bytes
+        let mut stream = PackStream::new(&base);
+        stream.add_data_reader("upload", payload.len() as u32, &payload[..]).unwrap();
+        let mut sync_out = Vec::new();
+        stream.write_to(&mut sync_out).unwrap();
+        assert_eq!(sync_out, out);
+
+        // A reader shorter than announced fails instead of truncating the pack
+        let mut short = PackStream::new(&base);
+        short.add_data_reader("upload", 10, &payload[..5]).unwrap();
+        let e = short.write_to(&mut Vec::new()).unwrap_err();
+        assert_eq!(e.io_error_kind(), Some(std::io::ErrorKind::UnexpectedEof));
+        assert_eq!(e.element.as_deref(), Some("upload"));
+    }
+
+    #[tokio::test]
+    async fn test_write_pack_async_never_leaves_a_short_frame_open() {
+        // A pack that can't be serialized is rejected before the size prefix
+        let mut mixed = Element::new("mixed".to_string(), ValueType::Int);
+        mixed.values = vec![Value::Int(1), Value::Str("x".into())];
+        let mut bad = sample();
+        bad.add_element(mixed).unwrap();
+        let mut w = FailingWriter { limit: 1 << 20, ..Default::default() };
+        let e = write_pack_async(&mut w, &bad).await.unwrap_err();
+        assert_eq!(e.kind, PackErrorKind::InvalidType);
+        assert_eq!(e.element.as_deref(), Some("mixed"));
+        assert!(w.written.is_empty() && !w.shut_down);
+
+        // A write failing after the prefix went out closes the stream
+        let mut w = FailingWriter { limit: 10, ..Default::default() };
+        let e = write_pack_async(&mut w, &sample()).await.unwrap_err();
+        assert_eq!(e.io_error_kind(), Some(io::ErrorKind::BrokenPipe));
+        assert_eq!(w.written.len(), 10);
+        assert!(w.shut_down);
+    }
+}
@@ -0,0 +1,148 @@
+// tests/pack_json_test.rs - Pack <-> JSON conversion
+
+use mayaqua::*;
+use serde_json::json;
+use std::io::Cursor;
+
+fn array_element(name: &str, group: &str, values: Vec<Value>) -> Element {
+    let mut e = Element::new(name.to_string(), values[0].value_type());
+    e.values = values;
+    e.json_hint_is_array = true;
+    e.json_hint_group_name = group.to_string();
+    e
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_to_json_scalars() {
+        let mut pack = Pack::new();
+        pack.add_str("hubname", "VPN");
+        pack.add_uni_str("msg", "hello");
+        pack.add_int("max_connection", 8);
+        pack.add_bool("use_encrypt", true);
+        pack.add_data("random", vec![1, 2, 3]);
+        pack.add_ip("client_ip", "192.168.10.100");
+
+        let mut created = Element::new("created".to_string(), ValueType::Int64);
+        created.values.push(Value::Int64(1_514_764_800_123));
+        created.json_hint_is_date_time = true;
+        pack.add_element(created).unwrap();
+
+        assert_eq!(
+            pack_to_json(&pack),
+            json!({
+                "hubname_str": "VPN",
+                "msg_utf": "hello",
+                "max_connection_u32": 8,
+                "use_encrypt_bool": true,
+                "random_bin": "AQID",
+                "client_ip_ip": "192.168.10.100",
+                "created_dt": "2018-01-01T00:00:00.123Z",
+            })
+        );
+    }
+
+    #[test]
+    fn test_ip_triplet_without_hints() {
+        let mut pack = Pack::new();
+        pack.add_ip("client_ip", "10.0.0.1");
+        let buf = pack.to_buf().unwrap();
+        let received = read_pack(&mut Cursor::new(&buf)).unwrap();
+
+        assert_eq!(pack_to_json(&received), json!({ "client_ip_ip": "10.0.0.1" }));
+    }
+
+    #[test]
+    fn test_grouped_arrays_round_trip() {
+        let mut pack = Pack::new();
+        pack.add_str("HubName", "DEFAULT");
+        pack.add_element(array_element(
+            "Name",
+            "SessionList",
+            vec![Value::Str("SID-A".into()), Value::Str("SID-B".into())],
+        ))
+        .unwrap();
+        pack.add_element(array_element("Connections", "SessionList", vec![Value::Int(1), Value::Int(4)]))
+            .unwrap();
+
+        let j = pack_to_json(&pack);
+        assert_eq!(
+            j,
+            json!({
+                "SessionList": [
+                    { "Name_str": "SID-A", "Connections_u32": 1 },
+                    { "Name_str": "SID-B", "Connections_u32": 4 },
+                ],
+                "HubName_str": "DEFAULT",
+            })
+        );
+
+        let back = json_to_pack(&j).unwrap();
+        assert_eq!(back.get_str_ex("Name", 1), "SID-B");
+        assert_eq!(back.get_int_ex("Connections", 1), 4);
+        assert_eq!(pack_to_json(&back), j);
+    }
+
+    #[test]
+    fn test_json_to_pack() {
+        let j = json!({
+            "HubName_str": "VPN",
+            "Online_bool": "true",
+            "Traffic_u64": 1u64 << 40,
+            "Cert_bin": "AQID",
+            "Expires_dt": "2018-01-01T00:00:00.123Z",
+            "Gateway_ip": "fe80::1%3",
+            "Ignored_xyz": 1,
+        });
+        let pack = json_to_pack(&j).unwrap();
+
+        assert_eq!(pack.get_str("HubName"), "VPN");
+        assert!(pack.get_bool("Online"));
+        assert_eq!(pack.get_data("Cert"), vec![1, 2, 3]);
+        assert!(pack.get_bool("Gateway@ipv6_bool"));
+        assert_eq!(pack.get_int("Gateway@ipv6_scope_id"), 3);
+        assert!(pack.get_element("Ignored", None).is_none());
+        assert_eq!(pack_to_json(&pack)["Expires_dt"], "2018-01-01T00:00:00.123Z");
+        assert_eq!(pack_to_json(&pack)["Gateway_ip"], "fe80::1%3");
+
+        assert!(json_to_pack(&json!([1, 2])).is_err());
+        assert!(json_to_pack(&json!({ "Cert_bin": "not base64!" })).is_err());
+    }
+
+    #[test]
+    fn test_json_numbers_out_of_range() {
+        for (name, key, v) in [
+            ("Port", "Port_u32", json!(-1)),
+            ("Port", "Port_u32", json!(4294967296u64)),
+            ("Port", "Port_u32", json!(1.5)),
+            ("Port", "Port_u32", json!("-1")),
+            ("Traffic", "Traffic_u64", json!(-1)),
+            ("Traffic", "Traffic_u64", json!(1.5)),
+            ("Traffic", "Traffic_u64", json!(1e20)),
+            ("Expires", "Expires_dt", json!(-1)),
+        ] {
+            let e = json_to_pack(&json!({ key: v })).unwrap_err();
+            assert_eq!(e.kind, PackErrorKind::InvalidType, "{} {}", key, v);
+            assert_eq!(e.element.as_deref(), Some(name));
+        }
+
+        let pack = json_to_pack(&json!({ "Port_u32": 4294967295u64, "Mtu_u32": 1400.0 })).unwrap();
+        assert_eq!(pack.get_int("Port"), u32::MAX);
+        assert_eq!(pack.get_int("Mtu"), 1400);
+    }
+
+    #[test]
+    fn test_empty_group_round_trip() {
+        let mut pack = Pack::new();
+        pack.add_int("NumSession", 0);
+        pack.set_current_json_group_name("SessionList");
+        pack.set_current_json_group_name("");
+
+        let j = pack_to_json(&pack);
+        assert_eq!(j, json!({ "SessionList": [], "NumSession_u32": 0 }));
+        assert_eq!(pack_to_json(&json_to_pack(&j).unwrap()), j);
+    }
+}
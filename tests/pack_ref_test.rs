@@ -0,0 +1,61 @@
+// tests/pack_ref_test.rs - Zero-copy borrowed pack parser
+
+use mayaqua::*;
+use std::io::Cursor;
+
+fn sample_buf() -> Vec<u8> {
+    let mut pack = Pack::new();
+    pack.add_str("hello", "SoftEther VPN Server");
+    pack.add_uni_str("msg", "hub message");
+    pack.add_int("build", 9799);
+    pack.add_data("cert", vec![0x30; 1024]);
+    pack.to_buf().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_borrows_from_buffer() {
+        let buf = sample_buf();
+        let p = PackRef::parse(&buf).unwrap();
+
+        assert_eq!(p.get_num(), 4);
+        assert_eq!(p.get_str("HELLO"), "SoftEther VPN Server");
+        assert_eq!(p.get_uni_str("msg"), "hub message");
+        assert_eq!(p.get_int("build"), 9799);
+        assert_eq!(p.get_int("hello"), 0);
+
+        let cert = p.get_data("cert");
+        assert_eq!(cert.len(), 1024);
+        let range = buf.as_ptr_range();
+        assert!(range.contains(&cert.as_ptr()));
+    }
+
+    #[test]
+    fn test_to_owned_matches_read_pack() {
+        let buf = sample_buf();
+        let owned = PackRef::parse(&buf).unwrap().to_owned();
+        let read = read_pack(&mut Cursor::new(&buf)).unwrap();
+
+        assert_eq!(owned.to_buf().unwrap(), read.to_buf().unwrap());
+    }
+
+    #[test]
+    fn test_truncated_and_duplicate_input() {
+        let buf = sample_buf();
+        for n in [0, 3, 10, buf.len() - 1] {
+            assert!(PackRef::parse(&buf[..n]).is_err());
+        }
+
+        // Two elements named "a"
+        let dup: Vec<u8> = [
+            &[0, 0, 0, 2][..],
+            &[0, 0, 0, 2, b'a', 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1],
+            &[0, 0, 0, 2, b'a', 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2],
+        ]
+        .concat();
+        assert_eq!(PackRef::parse(&dup).unwrap_err().kind, PackErrorKind::SameNameExists);
+    }
+}
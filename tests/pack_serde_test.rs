@@ -0,0 +1,127 @@
+// tests/pack_serde_test.rs - serde Serializer/Deserializer for Pack
+
+use mayaqua::*;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::net::IpAddr;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct AuthRequest {
+    method: String,
+    hubname: String,
+    authtype: u32,
+    #[serde(with = "mayaqua::pack_serde::bytes")]
+    secure_password: Vec<u8>,
+    #[serde(with = "mayaqua::pack_serde::uni_str")]
+    client_str: String,
+    use_encrypt: bool,
+    total_send: u64,
+    #[serde(with = "mayaqua::pack_serde::ip")]
+    client_ip: IpAddr,
+    ports: Vec<u32>,
+    note: Option<String>,
+}
+
+fn sample() -> AuthRequest {
+    AuthRequest {
+        method: "login".to_string(),
+        hubname: "VPN".to_string(),
+        authtype: 1,
+        secure_password: vec![0xAA; 20],
+        client_str: "SoftEther VPN Client".to_string(),
+        use_encrypt: true,
+        total_send: 1 << 40,
+        client_ip: "2001:db8::1".parse().unwrap(),
+        ports: vec![443, 992, 5555],
+        note: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_pack_types_and_hints() {
+        let pack = to_pack(&sample()).unwrap();
+
+        assert_eq!(pack.get_element("method", None).unwrap().type_, ValueType::Str);
+        assert_eq!(pack.get_element("client_str", None).unwrap().type_, ValueType::UniStr);
+        assert_eq!(pack.get_element("secure_password", None).unwrap().type_, ValueType::Data);
+        assert_eq!(pack.get_element("total_send", None).unwrap().type_, ValueType::Int64);
+        assert!(pack.get_element("use_encrypt", None).unwrap().json_hint_is_bool);
+        assert!(pack.get_element("client_ip", None).unwrap().json_hint_is_ip);
+        assert!(pack.get_bool("client_ip@ipv6_bool"));
+
+        let ports = pack.get_element("ports", None).unwrap();
+        assert!(ports.json_hint_is_array);
+        assert_eq!(ports.num_value(), 3);
+        assert!(pack.get_element("note", None).is_none());
+    }
+
+    #[test]
+    fn test_round_trip_over_the_wire() {
+        let buf = to_pack(&sample()).unwrap().to_buf().unwrap();
+        let received = read_pack(&mut Cursor::new(&buf)).unwrap();
+
+        let decoded: AuthRequest = from_pack(&received).unwrap();
+        assert_eq!(decoded, sample());
+    }
+
+    #[test]
+    fn test_missing_and_borrowed_fields() {
+        #[derive(Deserialize)]
+        struct Hello<'a> {
+            hello: &'a str,
+            build: u32,
+            #[serde(default)]
+            version: u32,
+        }
+
+        let mut pack = Pack::new();
+        pack.add_str("Hello", "SoftEther VPN Server");
+        pack.add_int("build", 9999);
+        let hello: Hello = from_pack(&pack).unwrap();
+        assert_eq!(hello.hello, "SoftEther VPN Server");
+        assert_eq!(hello.build, 9999);
+        assert_eq!(hello.version, 0);
+
+        let mut pack = Pack::new();
+        pack.add_str("hello", "x");
+        assert!(from_pack::<Hello>(&pack).is_err());
+    }
+
+    #[test]
+    fn test_time64_field() {
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Session {
+            #[serde(with = "mayaqua::pack_serde::time64")]
+            created: SystemTime,
+        }
+
+        let s = Session { created: UNIX_EPOCH + Duration::from_millis(1_514_764_800_123) };
+        let pack = to_pack(&s).unwrap();
+        let e = pack.get_element("created", Some(ValueType::Int64)).unwrap();
+        assert!(e.json_hint_is_date_time);
+        assert_eq!(pack_to_json(&pack)["created_dt"], "2018-01-01T00:00:00.123Z");
+        assert_eq!(from_pack::<Session>(&pack).unwrap(), s);
+    }
+
+    #[test]
+    fn test_empty_vec_round_trip() {
+        let empty = AuthRequest { ports: vec![], ..sample() };
+        let pack = to_pack(&empty).unwrap();
+        assert!(pack.get_element("ports", None).is_none());
+
+        let received = read_pack(&mut Cursor::new(pack.to_buf().unwrap())).unwrap();
+        assert_eq!(from_pack::<AuthRequest>(&received).unwrap(), empty);
+
+        // Other required fields are still reported missing
+        let mut pack = to_pack(&empty).unwrap();
+        pack.remove("authtype");
+        let err = from_pack::<AuthRequest>(&pack).unwrap_err();
+        assert_eq!(err.to_string(), "missing field `authtype`");
+    }
+}
@@ -15,7 +15,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     pack.add_bool("test_bool", true);
     pack.add_data("test_data", vec![0x01, 0x02, 0x03, 0x04]);
     
-    println!("Pack has {} elements", pack.elements.len());
+    println!("Pack has {} elements", pack.get_num());
     
     // Test serialization
     let buf = pack.to_buf()?;
@@ -25,10 +25,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Test deserialization
     let mut cursor = Cursor::new(&buf);
     let pack2 = read_pack(&mut cursor)?;
-    println!("Deserialized pack has {} elements", pack2.elements.len());
+    println!("Deserialized pack has {} elements", pack2.get_num());
     
     // Verify data integrity
-    for elem in &pack2.elements {
+    for elem in &pack2 {
         println!("Element: {}, Type: {:?}, Values: {}", 
             elem.name, elem.type_ as u32, elem.values.len());
     }
@@ -56,8 +56,310 @@ mod tests {
         let mut cursor = Cursor::new(&buf);
         let pack2 = read_pack(&mut cursor).unwrap();
         
-        assert_eq!(pack2.elements.len(), 2);
+        assert_eq!(pack2.get_num(), 2);
         assert_eq!(pack2.get_str("test"), "value");
         assert_eq!(pack2.get_int("number"), 123);
     }
+
+    #[test]
+    fn test_uni_str_round_trip() {
+        let mut pack = Pack::new();
+        pack.add_uni_str("hub_msg", "Welcome こんにちは");
+        pack.add_uni_str("empty", "");
+
+        let buf = pack.to_buf().unwrap();
+        let pack2 = read_pack(&mut Cursor::new(&buf)).unwrap();
+
+        assert_eq!(pack2.get_uni_str("hub_msg"), "Welcome こんにちは");
+        assert_eq!(pack2.get_uni_str("empty"), "");
+        assert_eq!(pack2.get_str("hub_msg"), "");
+    }
+
+    #[test]
+    fn test_uni_str_wire_layout() {
+        let mut pack = Pack::new();
+        pack.add_uni_str("m", "ab");
+        pack.add_uni_str("e", "");
+        let buf = pack.to_buf().unwrap();
+
+        let expected: Vec<u8> = [
+            &[0, 0, 0, 2][..],
+            // "m": name, type UniStr, 1 value, size 3 = "ab" + NUL
+            &[0, 0, 0, 2, b'm', 0, 0, 0, 3, 0, 0, 0, 1, 0, 0, 0, 3, b'a', b'b', 0],
+            // "e": the empty string is a lone NUL with size 1
+            &[0, 0, 0, 2, b'e', 0, 0, 0, 3, 0, 0, 0, 1, 0, 0, 0, 1, 0],
+        ]
+        .concat();
+        assert_eq!(buf, expected);
+
+        // Some peers send the empty string with size 0
+        let zero: Vec<u8> = [
+            &[0, 0, 0, 1][..],
+            &[0, 0, 0, 2, b'e', 0, 0, 0, 3, 0, 0, 0, 1, 0, 0, 0, 0],
+        ]
+        .concat();
+        let pack2 = read_pack(&mut Cursor::new(&zero)).unwrap();
+        assert_eq!(pack2.get_uni_str("e"), "");
+    }
+
+    #[test]
+    fn test_read_pack_with_limits() {
+        let mut pack = Pack::new();
+        pack.add_str("hello", "SoftEther");
+        pack.add_data("blob", vec![0u8; 1000]);
+        let buf = pack.to_buf().unwrap();
+
+        let limits = PackLimits::default();
+        assert!(read_pack_with_limits(&mut Cursor::new(&buf), &limits).is_ok());
+
+        let tight = [
+            PackLimits { max_total_size: buf.len() as u64 - 1, ..limits },
+            PackLimits { max_elements: 1, ..limits },
+            PackLimits { max_name_len: 4, ..limits },
+            PackLimits { max_value_size: 999, ..limits },
+        ];
+        for l in &tight {
+            assert!(read_pack_with_limits(&mut Cursor::new(&buf), l).is_err(), "{:?}", l);
+        }
+        assert!(PackRef::parse_with_limits(&buf, &tight[3]).is_err());
+    }
+
+    #[test]
+    fn test_hostile_sizes_fail_without_data() {
+        // A single Data value claiming 300MB, followed by only 3 bytes
+        let hostile: Vec<u8> = [
+            &[0, 0, 0, 1][..],
+            &[0, 0, 0, 2, b'd', 0, 0, 0, 1, 0, 0, 0, 1],
+            &(300u32 * 1024 * 1024).to_be_bytes(),
+            &[1, 2, 3],
+        ]
+        .concat();
+        assert_eq!(read_pack(&mut Cursor::new(&hostile)).unwrap_err().kind, PackErrorKind::IoError);
+
+        // A name longer than SoftEther allows
+        let long_name: Vec<u8> = [&[0, 0, 0, 1][..], &[0, 0, 0, 65], &[b'n'; 64]].concat();
+        assert_eq!(read_pack(&mut Cursor::new(&long_name)).unwrap_err().kind, PackErrorKind::SizeOver);
+    }
+
+    #[test]
+    fn test_int64_and_time64() {
+        let expires = std::time::UNIX_EPOCH + std::time::Duration::from_millis(1_700_000_000_123);
+
+        let mut pack = Pack::new();
+        pack.add_int64("total_recv", 5_000_000_000);
+        assert!(pack.add_time64("expires", expires).unwrap().json_hint_is_date_time);
+        pack.add_int64("never", 0);
+
+        let buf = pack.to_buf().unwrap();
+        let pack2 = read_pack(&mut Cursor::new(&buf)).unwrap();
+
+        assert_eq!(pack2.get_int64("total_recv"), 5_000_000_000);
+        assert_eq!(pack2.get_int64_ex("total_recv", 1), 0);
+        assert_eq!(pack2.get_int64("expires"), 1_700_000_000_123);
+        assert_eq!(pack2.get_time64("expires"), Some(expires));
+        assert_eq!(pack2.get_time64("never"), None);
+        assert_eq!(pack2.get_time64("missing"), None);
+        assert_eq!(pack2.get_int("total_recv"), 0);
+
+        // Any wire value is accepted without panicking, even where SystemTime
+        // can't hold it (its range is platform-dependent)
+        let mut far = Pack::new();
+        far.add_int64("expires", u64::MAX);
+        assert_eq!(far.get_time64("expires"), time64_to_system_time(u64::MAX));
+        assert_eq!(time64_to_system_time(1_700_000_000_123), Some(expires));
+    }
+
+    #[test]
+    fn test_ip_addr_triplet() {
+        use std::net::IpAddr;
+
+        let v4: IpAddr = "192.168.10.1".parse().unwrap();
+        let v6: IpAddr = "fe80::1234".parse().unwrap();
+
+        let mut pack = Pack::new();
+        pack.add_ip_addr("client_ip", v4);
+        pack.add_ip_addr_with_scope("gateway", v6, 7);
+        assert_eq!(pack.get_num(), 8);
+        assert!(pack.iter().all(|e| e.json_hint_is_ip));
+
+        let buf = pack.to_buf().unwrap();
+        let pack2 = read_pack(&mut Cursor::new(&buf)).unwrap();
+
+        assert_eq!(pack2.get_ip_addr("client_ip"), Some(v4));
+        assert_eq!(pack2.get_ip("client_ip"), "192.168.10.1");
+        assert_eq!(pack2.get_ip_addr_with_scope("gateway"), Some((v6, 7)));
+        assert_eq!(pack2.get_int("gateway"), 0);
+        assert_eq!(pack2.get_ip_addr("missing"), None);
+
+        // Old peers send only the IPv4 int
+        let mut old = Pack::new();
+        old.add_int("server_ip", 0x0A000001);
+        assert_eq!(old.get_ip_addr("server_ip"), Some("10.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_add_ex_builders_and_rows() {
+        let sessions = [("SID-A", 1u32, "10.0.0.1"), ("SID-B", 4, "fe80::1")];
+
+        let mut pack = Pack::new();
+        pack.add_str("HubName", "DEFAULT");
+        pack.set_current_json_group_name("SessionList");
+        for (i, (name, conns, ip)) in sessions.iter().enumerate() {
+            let total = sessions.len() as u32;
+            pack.add_str_ex("Name", name, i as u32, total).unwrap();
+            pack.add_int_ex("Connections", *conns, i as u32, total).unwrap();
+            pack.add_ip_addr_ex("ClientIP", ip.parse().unwrap(), i as u32, total).unwrap();
+        }
+        pack.set_current_json_group_name("");
+
+        // An index past total and a second plain add of the same name both fail
+        assert!(pack.add_int_ex("Connections", 9, 2, 2).is_none());
+        assert!(pack.add_str("HubName", "again").is_none());
+
+        // A larger total on a later call grows the element instead of dropping the value
+        let mut grown = Pack::new();
+        grown.add_int_ex("n", 1, 0, 2).unwrap();
+        let e = grown.add_int_ex("n", 5, 4, 5).unwrap();
+        assert_eq!(e.num_value(), 5);
+        assert_eq!((0..5).map(|i| grown.get_int_ex("n", i)).collect::<Vec<_>>(), [1, 0, 0, 0, 5]);
+        grown.add_ip_addr_ex("ip", "10.0.0.1".parse().unwrap(), 0, 1).unwrap();
+        grown.add_ip_addr_ex("ip", "fe80::1".parse().unwrap(), 2, 3).unwrap();
+        assert_eq!(grown.get_ip_addr_ex("ip", 2), Some("fe80::1".parse().unwrap()));
+        assert_eq!(grown.get_index_count("ip@ipv6_array"), 3);
+
+        let name = pack.get_element("Name", None).unwrap();
+        assert!(name.json_hint_is_array);
+        assert_eq!(name.json_hint_group_name, "SessionList");
+
+        let buf = pack.to_buf().unwrap();
+        let pack2 = read_pack(&mut Cursor::new(&buf)).unwrap();
+        assert_eq!(pack2.get_index_count("Name"), 2);
+        assert_eq!(pack2.get_index_count("missing"), 0);
+
+        let rows: Vec<_> = pack2
+            .rows("Name")
+            .map(|r| (r.get_str("Name"), r.get_int("Connections"), r.get_ip_addr("ClientIP").unwrap()))
+            .collect();
+        assert_eq!(
+            rows,
+            sessions
+                .iter()
+                .map(|(n, c, ip)| (n.to_string(), *c, ip.parse().unwrap()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(pack.group_rows("sessionlist").count(), 2);
+    }
+
+    #[test]
+    fn test_redacted_display_hides_secrets() {
+        let mut pack = Pack::new();
+        pack.add_str("username", "alice");
+        pack.add_data("secure_password", vec![0xAB; 20]);
+        pack.add_str("Plain_Password", "hunter2");
+        pack.add_int("max_connection", 8);
+
+        let s = pack.redacted().to_string();
+        assert_eq!(
+            s,
+            "{username: \"alice\", secure_password: <redacted 20 bytes>, \
+             Plain_Password: <redacted 7 chars>, max_connection: 8}"
+        );
+        assert!(is_secret_element_name("SESSION_KEY"));
+
+        // {:?} goes through the same redaction, for the pack and each element
+        assert_eq!(format!("{:?}", pack), format!("Pack {}", s));
+        let e = format!("{:?}", pack.get_element("Plain_Password", None).unwrap());
+        assert!(e.contains("<redacted 7 chars>") && !e.contains("hunter2"), "{}", e);
+        let e = format!("{:?}", pack.get_element("username", None).unwrap());
+        assert!(e.contains("\"alice\""), "{}", e);
+    }
+
+    #[test]
+    fn test_read_errors_carry_context() {
+        use std::error::Error;
+
+        // One element "x" with the unknown type code 9
+        let bad_type: Vec<u8> = [&[0, 0, 0, 1][..], &[0, 0, 0, 2, b'x', 0, 0, 0, 9]].concat();
+        let e = read_pack(&mut Cursor::new(&bad_type)).unwrap_err();
+        assert_eq!(e.kind, PackErrorKind::InvalidType);
+        assert_eq!(e.type_code, Some(9));
+        assert_eq!(e.element.as_deref(), Some("x"));
+        assert_eq!(e.offset, Some(9));
+
+        // Str "s" whose second byte is not UTF-8
+        let bad_utf8: Vec<u8> = [
+            &[0, 0, 0, 1][..],
+            &[0, 0, 0, 2, b's', 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, b'a', 0xff],
+        ]
+        .concat();
+        for e in [
+            read_pack(&mut Cursor::new(&bad_utf8)).unwrap_err(),
+            PackRef::parse(&bad_utf8).unwrap_err(),
+        ] {
+            assert_eq!(e.kind, PackErrorKind::Utf8);
+            assert_eq!(e.element.as_deref(), Some("s"));
+            assert_eq!(e.offset, Some(22));
+            assert!(e.source().is_some());
+        }
+
+        // Data "d" cut short after 3 of 8 bytes
+        let truncated: Vec<u8> = [
+            &[0, 0, 0, 1][..],
+            &[0, 0, 0, 2, b'd', 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 8, 1, 2, 3],
+        ]
+        .concat();
+        let e = read_pack(&mut Cursor::new(&truncated)).unwrap_err();
+        assert_eq!(e.kind, PackErrorKind::IoError);
+        assert_eq!(e.io_error_kind(), Some(std::io::ErrorKind::UnexpectedEof));
+        assert_eq!(e.offset, Some(24));
+        assert!(e.to_string().starts_with("IO error in element 'd' at offset 24"));
+    }
+
+    #[test]
+    fn test_strict_and_canonical_writing() {
+        let strict = WriteOptions { strict: true, ..Default::default() };
+
+        let mut pack = Pack::new();
+        pack.add_str("hello", "world");
+        assert!(pack.to_buf_with(&strict).is_ok());
+
+        let mut long = pack.clone();
+        long.add_int(&"n".repeat(64), 1);
+        let e = long.to_buf_with(&strict).unwrap_err();
+        assert_eq!(e.kind, PackErrorKind::SizeOver);
+        assert!(long.to_buf().is_ok());
+
+        // Each value is within MAX_VALUE_SIZE but together they exceed MAX_PACK_SIZE.
+        // The zeroed buffers are never touched, so this doesn't cost real memory.
+        let mut huge = Pack::new();
+        huge.add_data("a", vec![0u8; 300 << 20]);
+        huge.add_data("b", vec![0u8; 300 << 20]);
+        assert_eq!(huge.validate().unwrap_err().kind, PackErrorKind::SizeOver);
+        assert_eq!(huge.to_buf_with(&strict).unwrap_err().kind, PackErrorKind::SizeOver);
+
+        let mut spaced = pack.clone();
+        spaced.add_int("bad name", 1);
+        assert_eq!(spaced.validate().unwrap_err().kind, PackErrorKind::InvalidName);
+
+        // A value whose variant disagrees with the element type
+        let mut mixed = Element::new("mixed".to_string(), ValueType::Int);
+        mixed.values = vec![Value::Int(1), Value::Str("x".into())];
+        let mut typed = Pack::new();
+        typed.add_element(mixed).unwrap();
+        let e = typed.to_buf_with(&strict).unwrap_err();
+        assert_eq!(e.kind, PackErrorKind::InvalidType);
+        assert_eq!(e.element.as_deref(), Some("mixed"));
+
+        // Insertion order doesn't matter in canonical mode
+        let canonical = WriteOptions { canonical: true, ..strict };
+        let mut a = Pack::new();
+        a.add_int("b", 2);
+        a.add_int("a", 1);
+        let mut b = Pack::new();
+        b.add_int("a", 1);
+        b.add_int("b", 2);
+        assert_ne!(a.to_buf().unwrap(), b.to_buf().unwrap());
+        assert_eq!(a.to_buf_with(&canonical).unwrap(), b.to_buf_with(&canonical).unwrap());
+        assert_eq!(b.to_buf_with(&canonical).unwrap(), b.to_buf().unwrap());
+    }
 }
@@ -1,6 +1,6 @@
 //! Test the enhanced pack_types functionality
 
-use mayaqua::{Pack, Element, Value, ValueType};
+use mayaqua::{ConflictPolicy, Pack, PackErrorKind, Element, Value, ValueType};
 
 fn main() {
     println!("🚀 Enhanced Pack Types Test");
@@ -82,3 +82,116 @@ fn create_mock_server_response() -> Pack {
     
     pack
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mayaqua::{PackFieldError, PackFieldErrorKind};
+
+    #[test]
+    fn test_try_get_distinguishes_missing_from_zero() {
+        let mut pack = create_mock_server_response();
+        pack.add_int("error", 0);
+        pack.add_str("hello", "SoftEther");
+
+        assert_eq!(pack.try_get_int("error"), Ok(0));
+        assert_eq!(pack.try_get_int("mtu"), Ok(1500));
+        assert_eq!(pack.try_get_str("HELLO"), Ok("SoftEther"));
+
+        let missing = pack.try_get_int("no_such_field").unwrap_err();
+        assert_eq!(
+            missing,
+            PackFieldError {
+                name: "no_such_field".to_string(),
+                kind: PackFieldErrorKind::Missing,
+            }
+        );
+
+        let wrong = pack.try_get_data("hello").unwrap_err();
+        assert_eq!(
+            wrong.kind,
+            PackFieldErrorKind::WrongType {
+                expected: ValueType::Data,
+                found: ValueType::Str,
+            }
+        );
+
+        let range = pack.try_get_int_ex("mtu", 1).unwrap_err();
+        assert_eq!(range.kind, PackFieldErrorKind::IndexOutOfRange { index: 1, len: 1 });
+        assert!(range.to_string().contains("mtu"));
+    }
+
+    #[test]
+    fn test_borrowed_element_values() {
+        let mut pack = Pack::new();
+        pack.add_data("random", vec![7u8; 20]);
+
+        let e = pack.try_get_element("random", ValueType::Data).unwrap();
+        assert_eq!(e.data_value(0), Some(&[7u8; 20][..]));
+        assert_eq!(e.str_value(0), None);
+        assert_eq!(e.data_value(1), None);
+    }
+
+    #[test]
+    fn test_name_index_lookup_semantics() {
+        let mut pack = Pack::new();
+        pack.add_int("Port", 443);
+        // Duplicates are detected case-sensitively, lookups are case-insensitive
+        pack.add_str("port", "https");
+        assert!(pack.add_int("Port", 992).is_none());
+        pack.add_uni_str("Größe", "x");
+
+        assert_eq!(pack.get_int("PORT"), 443);
+        assert_eq!(pack.get_str("PoRt"), "https");
+        assert_eq!(pack.get_element("port", None).unwrap().name, "Port");
+        assert_eq!(pack.get_uni_str("GRÖSSE"), "x");
+        assert_eq!(pack.get_uni_str("größe"), "x");
+
+        let names: Vec<&str> = pack.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["Port", "port", "Größe"]);
+        assert_eq!(pack.elements().len(), pack.get_num());
+    }
+
+    #[test]
+    fn test_remove_set_merge_retain() {
+        let mut pack = Pack::new();
+        pack.add_str("method", "login");
+        pack.add_str("username", "alice");
+        pack.add_str("plain_password", "secret");
+        pack.add_ip_addr("client_ip", "10.0.0.1".parse().unwrap());
+
+        // Strip the password and rewrite the user in place
+        assert_eq!(pack.remove("PLAIN_PASSWORD").unwrap().get_str_value(0), "secret");
+        assert!(pack.remove("plain_password").is_none());
+        pack.set_str("username", "bob").unwrap();
+        pack.set_int("username", 7).unwrap();
+        assert_eq!(pack.get_int("username"), 7);
+        assert_eq!(pack.elements()[1].name, "username");
+
+        pack.set_ip_addr("client_ip", "2001:db8::2".parse().unwrap());
+        assert_eq!(pack.get_ip_addr("client_ip"), Some("2001:db8::2".parse().unwrap()));
+        assert_eq!(pack.get_num(), 6);
+
+        let mut node = Pack::new();
+        node.add_str("method", "other");
+        node.add_str("node_name", "edge-1");
+        let mut conflict = pack.clone();
+        let err = conflict.merge(node.clone(), ConflictPolicy::Error).unwrap_err();
+        assert_eq!(err.kind, PackErrorKind::SameNameExists);
+        assert_eq!(err.element.as_deref(), Some("method"));
+        assert_eq!(conflict.get_num(), pack.get_num());
+
+        let mut keep = pack.clone();
+        keep.merge(node.clone(), ConflictPolicy::KeepExisting).unwrap();
+        assert_eq!(keep.get_str("method"), "login");
+        assert_eq!(keep.get_str("node_name"), "edge-1");
+
+        pack.merge(node, ConflictPolicy::Overwrite).unwrap();
+        assert_eq!(pack.get_str("method"), "other");
+
+        pack.retain(|e| !e.name.starts_with("client_ip"));
+        assert_eq!(pack.get_ip_addr("client_ip"), None);
+        assert_eq!(pack.get_str("node_name"), "edge-1");
+        assert!(pack.add_str("client_ip", "re-added").is_some());
+    }
+}
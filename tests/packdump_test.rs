@@ -0,0 +1,92 @@
+// tests/packdump_test.rs - packdump binary: text/JSON -> pack -> table/JSON
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn packdump(args: &[&str], stdin: &[u8]) -> (bool, Vec<u8>) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_packdump"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    let out = child.wait_with_output().unwrap();
+    (out.status.success(), out.stdout)
+}
+
+const FIXTURE: &str = "\
+# hand-crafted welcome fragment
+session_name str SID-TEST-1
+msg unistr \"  hello  \"
+use_encrypt bool true
+max_connection int 8
+expires time 2018-01-01T00:00:00.123Z
+random data 0102ff
+client_ip ip 192.168.10.5
+port int 443
+port int 992
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_text_then_decode_table() {
+        let (ok, hex) = packdump(&["encode", "--hex"], FIXTURE.as_bytes());
+        assert!(ok);
+
+        let (ok, table) = packdump(&["decode", "--hex", "--bool", "use_encrypt", "--time", "expires"], &hex);
+        assert!(ok);
+        let table = String::from_utf8(table).unwrap();
+
+        let line = |name: &str| table.lines().find(|l| l.starts_with(name)).unwrap().to_string();
+        assert!(line("session_name").ends_with("\"SID-TEST-1\""));
+        assert!(line("msg").ends_with("\"  hello  \""));
+        assert!(line("use_encrypt").contains("bool") && line("use_encrypt").ends_with("true"));
+        assert!(line("expires").ends_with("2018-01-01T00:00:00.123Z"));
+        assert!(line("random").ends_with("0102ff"));
+        assert!(line("client_ip ").contains("ip") && line("client_ip ").ends_with("192.168.10.5"));
+        assert!(line("port").ends_with("443, 992"));
+        assert!(!table.contains("@ipv6"));
+    }
+
+    #[test]
+    fn test_json_round_trip_and_errors() {
+        let (ok, bin) = packdump(&["encode"], FIXTURE.as_bytes());
+        assert!(ok);
+        let (ok, json) = packdump(&["decode", "--json"], &bin);
+        assert!(ok);
+        let v: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(v["client_ip_ip"], "192.168.10.5");
+        assert_eq!(v["port_u32"], serde_json::json!([443, 992]));
+
+        // JSON input compiles back to the same bytes
+        let (ok, bin2) = packdump(&["encode", "--json"], &json);
+        assert!(ok);
+        assert_eq!(bin2, bin);
+
+        assert!(!packdump(&["decode"], &bin[..bin.len() - 1]).0);
+        assert!(!packdump(&["encode"], b"name nosuchtype 1\n").0);
+        assert!(!packdump(&["frobnicate"], b"").0);
+    }
+
+    #[test]
+    fn test_encode_text_with_aligned_columns() {
+        let aligned = "session_name    str\tSID  TEST\n\
+                       max_connection  int     8\n\
+                       \tport\t \tint  443\n";
+        let (ok, hex) = packdump(&["encode", "--hex"], aligned.as_bytes());
+        assert!(ok);
+        let (ok, table) = packdump(&["decode", "--hex"], &hex);
+        assert!(ok);
+        let table = String::from_utf8(table).unwrap();
+
+        let line = |name: &str| table.lines().find(|l| l.starts_with(name)).unwrap().to_string();
+        assert!(line("session_name").ends_with("\"SID  TEST\""));
+        assert!(line("max_connection").ends_with(" 8"));
+        assert!(line("port").ends_with(" 443"));
+    }
+}
@@ -0,0 +1,415 @@
+// tests/protocol_test.rs - Typed handshake messages
+
+use mayaqua::protocol::*;
+use mayaqua::*;
+use std::io::Cursor;
+
+// Reference login packs, generated by tools/gen_login_packs.py: an encoder
+// independent of this crate that mirrors ClientUploadAuth in
+// src/Cedar/Protocol.c of SoftEtherVPN_Stable v4.43-9799-beta. They are not
+// wire captures; rerun the script and paste its output to update them.
+
+// Login to hub "DEFAULT" as "vpn", anonymous
+const LOGIN_ANONYMOUS: &str = "\
+    00000014000000076d6574686f640000000200000001000000056c6f67696e000000086875626e616d650000\
+    0002000000010000000744454641554c5400000009757365726e616d6500000002000000010000000376706e\
+    0000000961757468747970650000000000000001000000000000000b636c69656e745f737472000000020000\
+    000100000014536f667445746865722056504e20436c69656e740000000b636c69656e745f76657200000000\
+    00000001000001bb0000000d636c69656e745f6275696c640000000000000001000026470000000970726f74\
+    6f636f6c0000000000000001000000000000000668656c6c6f000000020000000100000014536f6674457468\
+    65722056504e20436c69656e740000000876657273696f6e0000000000000001000001bb000000066275696c\
+    640000000000000001000026470000000a636c69656e745f69640000000000000001000000000000000f6d61\
+    785f636f6e6e656374696f6e0000000000000001000000010000000c7573655f656e63727970740000000000\
+    000001000000010000000d7573655f636f6d70726573730000000000000001000000000000001068616c665f\
+    636f6e6e656374696f6e0000000000000001000000000000001c726571756972655f6272696467655f726f75\
+    74696e675f6d6f646500000000000000010000000000000015726571756972655f6d6f6e69746f725f6d6f64\
+    6500000000000000010000000000000004716f730000000000000001000000010000000a756e697175655f69\
+    640000000100000001000000140000000000000000000000000000000000000000";
+
+// Login to hub "DEFAULT" as "vpn", password "vpn", server random 00 01 .. 13
+const LOGIN_PASSWORD: &str = "\
+    00000015000000076d6574686f640000000200000001000000056c6f67696e000000086875626e616d650000\
+    0002000000010000000744454641554c5400000009757365726e616d6500000002000000010000000376706e\
+    000000096175746874797065000000000000000100000001000000107365637572655f70617373776f726400\
+    000001000000010000001409aabdcf0bd28b416c19a219e71230aa132027c30000000b636c69656e745f7374\
+    72000000020000000100000014536f667445746865722056504e20436c69656e740000000b636c69656e745f\
+    7665720000000000000001000001bb0000000d636c69656e745f6275696c6400000000000000010000264700\
+    00000970726f746f636f6c0000000000000001000000000000000668656c6c6f000000020000000100000014\
+    536f667445746865722056504e20436c69656e740000000876657273696f6e0000000000000001000001bb00\
+    0000066275696c640000000000000001000026470000000a636c69656e745f69640000000000000001000000\
+    000000000f6d61785f636f6e6e656374696f6e0000000000000001000000010000000c7573655f656e637279\
+    70740000000000000001000000010000000d7573655f636f6d70726573730000000000000001000000000000\
+    001068616c665f636f6e6e656374696f6e0000000000000001000000000000001c726571756972655f627269\
+    6467655f726f7574696e675f6d6f646500000000000000010000000000000015726571756972655f6d6f6e69\
+    746f725f6d6f646500000000000000010000000000000004716f730000000000000001000000010000000a75\
+    6e697175655f69640000000100000001000000140000000000000000000000000000000000000000";
+
+// Login to hub "DEFAULT" as "vpn", plain password "secret"
+const LOGIN_PLAIN_PASSWORD: &str = "\
+    00000015000000076d6574686f640000000200000001000000056c6f67696e000000086875626e616d650000\
+    0002000000010000000744454641554c5400000009757365726e616d6500000002000000010000000376706e\
+    0000000961757468747970650000000000000001000000020000000f706c61696e5f70617373776f72640000\
+    000200000001000000067365637265740000000b636c69656e745f737472000000020000000100000014536f\
+    667445746865722056504e20436c69656e740000000b636c69656e745f7665720000000000000001000001bb\
+    0000000d636c69656e745f6275696c640000000000000001000026470000000970726f746f636f6c00000000\
+    00000001000000000000000668656c6c6f000000020000000100000014536f667445746865722056504e2043\
+    6c69656e740000000876657273696f6e0000000000000001000001bb000000066275696c6400000000000000\
+    01000026470000000a636c69656e745f69640000000000000001000000000000000f6d61785f636f6e6e6563\
+    74696f6e0000000000000001000000010000000c7573655f656e637279707400000000000000010000000100\
+    00000d7573655f636f6d70726573730000000000000001000000000000001068616c665f636f6e6e65637469\
+    6f6e0000000000000001000000000000001c726571756972655f6272696467655f726f7574696e675f6d6f64\
+    6500000000000000010000000000000015726571756972655f6d6f6e69746f725f6d6f646500000000000000\
+    010000000000000004716f730000000000000001000000010000000a756e697175655f696400000001000000\
+    01000000140000000000000000000000000000000000000000";
+
+// Login to hub "DEFAULT" as "vpn", certificate 30 82 01 0a, signature a5 x 8
+const LOGIN_CERT: &str = "\
+    00000016000000076d6574686f640000000200000001000000056c6f67696e000000086875626e616d650000\
+    0002000000010000000744454641554c5400000009757365726e616d6500000002000000010000000376706e\
+    0000000961757468747970650000000000000001000000030000000563657274000000010000000100000004\
+    3082010a000000057369676e000000010000000100000008a5a5a5a5a5a5a5a50000000b636c69656e745f73\
+    7472000000020000000100000014536f667445746865722056504e20436c69656e740000000b636c69656e74\
+    5f7665720000000000000001000001bb0000000d636c69656e745f6275696c64000000000000000100002647\
+    0000000970726f746f636f6c0000000000000001000000000000000668656c6c6f0000000200000001000000\
+    14536f667445746865722056504e20436c69656e740000000876657273696f6e0000000000000001000001bb\
+    000000066275696c640000000000000001000026470000000a636c69656e745f696400000000000000010000\
+    00000000000f6d61785f636f6e6e656374696f6e0000000000000001000000010000000c7573655f656e6372\
+    7970740000000000000001000000010000000d7573655f636f6d707265737300000000000000010000000000\
+    00001068616c665f636f6e6e656374696f6e0000000000000001000000000000001c726571756972655f6272\
+    696467655f726f7574696e675f6d6f646500000000000000010000000000000015726571756972655f6d6f6e\
+    69746f725f6d6f646500000000000000010000000000000004716f730000000000000001000000010000000a\
+    756e697175655f69640000000100000001000000140000000000000000000000000000000000000000";
+
+// Login to hub "DEFAULT" as "vpn", ticket 5a x 20
+const LOGIN_TICKET: &str = "\
+    00000015000000076d6574686f640000000200000001000000056c6f67696e000000086875626e616d650000\
+    0002000000010000000744454641554c5400000009757365726e616d6500000002000000010000000376706e\
+    000000096175746874797065000000000000000100000063000000077469636b657400000001000000010000\
+    00145a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a0000000b636c69656e745f7374720000000200000001\
+    00000014536f667445746865722056504e20436c69656e740000000b636c69656e745f766572000000000000\
+    0001000001bb0000000d636c69656e745f6275696c640000000000000001000026470000000970726f746f63\
+    6f6c0000000000000001000000000000000668656c6c6f000000020000000100000014536f66744574686572\
+    2056504e20436c69656e740000000876657273696f6e0000000000000001000001bb000000066275696c6400\
+    00000000000001000026470000000a636c69656e745f69640000000000000001000000000000000f6d61785f\
+    636f6e6e656374696f6e0000000000000001000000010000000c7573655f656e637279707400000000000000\
+    01000000010000000d7573655f636f6d70726573730000000000000001000000000000001068616c665f636f\
+    6e6e656374696f6e0000000000000001000000000000001c726571756972655f6272696467655f726f757469\
+    6e675f6d6f646500000000000000010000000000000015726571756972655f6d6f6e69746f725f6d6f646500\
+    000000000000010000000000000004716f730000000000000001000000010000000a756e697175655f696400\
+    00000100000001000000140000000000000000000000000000000000000000";
+fn unhex(s: &str) -> Vec<u8> {
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+}
+
+fn sample_welcome() -> Welcome {
+    Welcome {
+        session_name: "SID-ALICE-1".to_string(),
+        connection_name: "CID-2".to_string(),
+        max_connection: 8,
+        use_encrypt: true,
+        use_compress: false,
+        half_connection: false,
+        timeout: 20000,
+        qos: true,
+        session_key: [3u8; 20],
+        session_key_32: 0x12345678,
+        policies: vec![("Access".to_string(), 1), ("MaxConnection".to_string(), 8)],
+        udp_accel: None,
+    }
+}
+
+fn round_trip(p: &Pack) -> Pack {
+    read_pack(&mut Cursor::new(p.to_buf().unwrap())).unwrap()
+}
+
+fn sample_login(auth: AuthData) -> ClientAuth {
+    ClientAuth {
+        hubname: "VPN".to_string(),
+        username: "alice".to_string(),
+        auth,
+        client_str: "SoftEther VPN Client".to_string(),
+        client_ver: 443,
+        client_build: 9798,
+        protocol: protocol::CONNECTION_TCP,
+        client_id: 0,
+        max_connection: 8,
+        use_encrypt: true,
+        use_compress: false,
+        half_connection: false,
+        require_bridge_routing_mode: false,
+        require_monitor_mode: true,
+        qos: false,
+        unique_id: [0x11; 20],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_hello_round_trip() {
+        let hello = ServerHello {
+            hello: "SoftEther VPN Server".to_string(),
+            version: 443,
+            build: 9798,
+            random: [7u8; 20],
+            capabilities: vec![],
+        };
+        let p = round_trip(&hello.to_pack());
+        assert_eq!(p.get_str("hello"), "SoftEther VPN Server");
+        assert_eq!(p.get_data("random").len(), 20);
+        assert_eq!(ServerHello::from_pack(&p).unwrap(), hello);
+
+        // A short random is rejected instead of being zero-padded
+        let not_vpn = Err(ProtocolError::NotVpn(ERR_SERVER_IS_NOT_VPN));
+        let mut p = hello.to_pack();
+        p.set_data("random", vec![1, 2, 3]);
+        assert_eq!(ServerHello::from_pack(&p), not_vpn);
+
+        let mut p = hello.to_pack();
+        p.remove("build");
+        assert_eq!(ServerHello::from_pack(&p), not_vpn);
+        assert_eq!(ProtocolError::NotVpn(ERR_SERVER_IS_NOT_VPN).to_string(), "ERR_SERVER_IS_NOT_VPN");
+    }
+
+    #[test]
+    fn test_server_hello_from_http_body() {
+        let mut p = ServerHello {
+            hello: "SoftEther VPN Server".to_string(),
+            version: 443,
+            build: 9799,
+            random: [1u8; 20],
+            capabilities: vec![],
+        }
+        .to_pack();
+        p.add_int("udp_accel_v2", 1);
+        p.add_data("pencore", vec![0; 33]);
+
+        let hello = ServerHello::from_http_body(&p.to_buf().unwrap()).unwrap();
+        assert_eq!((hello.version, hello.build), (443, 9799));
+        assert_eq!(hello.capabilities, vec![("udp_accel_v2".to_string(), 1)]);
+        assert_eq!(hello.capability("UDP_ACCEL_V2"), Some(1));
+        assert_eq!(hello.capability("missing"), None);
+        assert!(hello.supports_udp_accel_v2());
+
+        let old = ServerHello { build: protocol::UDP_ACCEL_V2_MIN_BUILD - 1, ..hello };
+        assert!(!old.supports_udp_accel_v2());
+        assert!(old.is_build_at_least(9000));
+
+        let not_vpn = Err(ProtocolError::NotVpn(ERR_SERVER_IS_NOT_VPN));
+        for body in [
+            &b"<!DOCTYPE html><html><body>It works!</body></html>"[..],
+            b"\r\n  <HTML><head></head></html>",
+            b"",
+            b"\x00\x00\x00\x01garbage",
+        ] {
+            assert_eq!(ServerHello::from_http_body(body), not_vpn, "{:?}", body);
+        }
+
+        // An error reply is not mistaken for a non-VPN server
+        let err = ErrorReply { error: 3 }.to_pack().to_buf().unwrap();
+        assert_eq!(ServerHello::from_http_body(&err), Err(ProtocolError::Server(3)));
+    }
+
+    #[test]
+    fn test_client_auth_every_auth_type() {
+        let cases = [
+            (AuthData::Anonymous, protocol::CLIENT_AUTHTYPE_ANONYMOUS),
+            (AuthData::Password { secure_password: [9u8; 20] }, protocol::CLIENT_AUTHTYPE_PASSWORD),
+            (
+                AuthData::PlainPassword { plain_password: "secret".to_string() },
+                protocol::CLIENT_AUTHTYPE_PLAIN_PASSWORD,
+            ),
+            (
+                AuthData::Cert { cert: vec![0x30, 0x82], sign: vec![1; 128] },
+                protocol::CLIENT_AUTHTYPE_CERT,
+            ),
+            (AuthData::Ticket { ticket: [5u8; 20] }, protocol::AUTHTYPE_TICKET),
+        ];
+
+        for (auth, authtype) in cases {
+            let login = sample_login(auth);
+            let p = round_trip(&login.to_pack());
+            assert_eq!(p.get_str("method"), "login");
+            assert_eq!(p.get_int("authtype"), authtype);
+            assert_eq!(ClientAuth::from_pack(&p).unwrap(), login);
+        }
+    }
+
+    #[test]
+    fn test_client_auth_validation() {
+        let mut p = sample_login(AuthData::Password { secure_password: [0u8; 20] }).to_pack();
+        p.remove("secure_password");
+        assert!(matches!(ClientAuth::from_pack(&p), Err(ProtocolError::Field(ref e)) if e.name == "secure_password"));
+
+        let mut p = sample_login(AuthData::Anonymous).to_pack();
+        p.set_int("authtype", 42);
+        assert!(matches!(ClientAuth::from_pack(&p), Err(ProtocolError::InvalidValue { ref name, .. }) if name == "authtype"));
+
+        let mut p = sample_login(AuthData::Anonymous).to_pack();
+        p.set_str("method", "additional_connect");
+        assert!(ClientAuth::from_pack(&p).is_err());
+
+        // A missing unique_id reads as zero, a malformed one is rejected
+        let mut p = sample_login(AuthData::Anonymous).to_pack();
+        p.remove("unique_id");
+        assert_eq!(ClientAuth::from_pack(&p).unwrap().unique_id, [0; 20]);
+        p.add_data("unique_id", vec![1; 16]);
+        assert!(matches!(ClientAuth::from_pack(&p), Err(ProtocolError::InvalidValue { ref name, .. }) if name == "unique_id"));
+    }
+
+    #[test]
+    fn test_welcome_and_error_reply() {
+        let welcome = sample_welcome();
+        assert_eq!(Welcome::from_pack(&round_trip(&welcome.to_pack())).unwrap(), welcome);
+
+        // An error reply is reported as such, not as a missing session_name
+        let err = ErrorReply { error: 9 }.to_pack();
+        assert_eq!(Welcome::from_pack(&err), Err(ProtocolError::Server(9)));
+        assert_eq!(ErrorReply::from_pack(&round_trip(&err)).unwrap().error, 9);
+        assert!(ErrorReply::from_pack(&Pack::new()).is_err());
+    }
+
+    #[test]
+    fn test_additional_connect_round_trip() {
+        let req = AdditionalConnect {
+            session_key: [0xAB; 20],
+            client_str: "SoftEther VPN Client".to_string(),
+            client_ver: 443,
+            client_build: 9798,
+        };
+        let p = round_trip(&req.to_pack());
+        assert_eq!(p.get_str("method"), "additional_connect");
+        assert_eq!(AdditionalConnect::from_pack(&p).unwrap(), req);
+
+        let login = sample_login(AuthData::Anonymous).to_pack();
+        assert!(AdditionalConnect::from_pack(&login).is_err());
+    }
+
+    #[test]
+    fn test_client_auth_builders_match_reference_packs() {
+        let random: Sha1Sum = std::array::from_fn(|i| i as u8);
+        let hashed = hash_password("vpn", "vpn");
+        let cases = [
+            (ClientAuth::anonymous("DEFAULT", "vpn"), LOGIN_ANONYMOUS),
+            (ClientAuth::password("DEFAULT", "vpn", &hashed, &random), LOGIN_PASSWORD),
+            (ClientAuth::plain_password("DEFAULT", "vpn", "secret"), LOGIN_PLAIN_PASSWORD),
+            (ClientAuth::cert("DEFAULT", "vpn", vec![0x30, 0x82, 0x01, 0x0a], vec![0xa5; 8]), LOGIN_CERT),
+            (ClientAuth::ticket("DEFAULT", "vpn", [0x5a; 20]), LOGIN_TICKET),
+        ];
+
+        for (login, reference) in cases {
+            let reference = unhex(reference);
+            assert_eq!(login.to_pack().to_buf().unwrap(), reference, "{:?}", login.auth);
+            let p = read_pack(&mut Cursor::new(&reference)).unwrap();
+            assert_eq!(ClientAuth::from_pack(&p).unwrap(), login);
+        }
+    }
+
+    #[test]
+    fn test_welcome_defaults_and_clamping() {
+        let mut p = Pack::new();
+        p.add_str("session_name", "SID-ALICE-1");
+        p.add_str("connection_name", "CID-2");
+        p.add_data("session_key", vec![3; 20]);
+        p.add_int("policy:MaxConnection", 4);
+        p.add_bool("policy:NoQoS", true);
+        p.add_str("policy:Comment", "not an Int");
+
+        // The C client defaults: one connection, 30 second timeout
+        let w = Welcome::from_pack(&p).unwrap();
+        assert_eq!((w.max_connection, w.timeout), (1, protocol::TIMEOUT_DEFAULT));
+        assert!(!w.qos && w.udp_accel.is_none());
+        assert_eq!(w.policy("maxconnection"), Some(4));
+        assert_eq!(w.policy("NoQoS"), Some(1));
+        assert_eq!(w.policy("Comment"), None);
+
+        p.add_int("max_connection", 100);
+        p.add_int("timeout", 1000);
+        let w = Welcome::from_pack(&p).unwrap();
+        assert_eq!((w.max_connection, w.timeout), (protocol::MAX_TCP_CONNECTION, protocol::TIMEOUT_MIN));
+
+        p.set_int("timeout", 600_000);
+        assert_eq!(Welcome::from_pack(&p).unwrap().timeout, protocol::TIMEOUT_MAX);
+
+        p.set_data("session_key", vec![3; 16]);
+        assert!(Welcome::from_pack(&p).is_err());
+    }
+
+    #[test]
+    fn test_welcome_udp_accel() {
+        let accel = UdpAccel {
+            version: 2,
+            server_ip: "203.0.113.7".parse().unwrap(),
+            server_port: 40000,
+            server_key: vec![0x42; protocol::UDP_ACCELERATION_COMMON_KEY_SIZE_V2],
+            server_cookie: 11,
+            client_cookie: 22,
+            use_encryption: true,
+            use_hmac: false,
+            fast_disconnect_detect: true,
+        };
+        let welcome = Welcome { udp_accel: Some(accel.clone()), ..sample_welcome() };
+        let p = round_trip(&welcome.to_pack());
+        assert!(p.get_bool("use_udp_acceleration"));
+        assert_eq!(p.get_data("udp_acceleration_server_key_v2").len(), 128);
+        assert_eq!(Welcome::from_pack(&p).unwrap(), welcome);
+
+        // The UDP acceleration key is as secret as the session key
+        let s = p.redacted().to_string();
+        assert!(s.contains("udp_acceleration_server_key_v2: <redacted 128 bytes>"), "{}", s);
+        assert!(format!("{:?}", welcome).contains("server_key: <redacted 128 bytes>"));
+
+        // Version 0 means 1, with the 20-byte key
+        let mut p = welcome.to_pack();
+        p.set_int("udp_acceleration_version", 0);
+        p.add_data("udp_acceleration_server_key", vec![0x24; 20]);
+        let v1 = Welcome::from_pack(&p).unwrap().udp_accel.unwrap();
+        assert_eq!((v1.version, v1.server_key.len()), (1, 20));
+        assert!(p.redacted().to_string().contains("udp_acceleration_server_key: <redacted 20 bytes>"));
+
+        // Unusable parameters disable acceleration instead of failing the login
+        let mut p = welcome.to_pack();
+        p.set_int("udp_acceleration_server_port", 0);
+        assert_eq!(Welcome::from_pack(&p).unwrap().udp_accel, None);
+
+        let mut p = welcome.to_pack();
+        p.set_data("udp_acceleration_server_key_v2", vec![0x42; 20]);
+        assert_eq!(Welcome::from_pack(&p).unwrap().udp_accel, None);
+
+        let mut p = welcome.to_pack();
+        p.set_ip_addr("udp_acceleration_server_ip", "0.0.0.0".parse().unwrap());
+        let w = Welcome::from_pack(&p).unwrap();
+        assert_eq!(w.udp_accel, None);
+        assert_eq!(w.session_name, "SID-ALICE-1");
+    }
+
+    #[test]
+    fn test_debug_hides_credentials() {
+        let logins = [
+            sample_login(AuthData::Password { secure_password: [0xAB; 20] }),
+            sample_login(AuthData::PlainPassword { plain_password: "hunter2".to_string() }),
+            sample_login(AuthData::Cert { cert: vec![0x30, 0x82], sign: vec![0xAB; 8] }),
+            sample_login(AuthData::Ticket { ticket: [0xAB; 20] }),
+        ];
+        for login in &logins {
+            let s = format!("{:?}", login);
+            assert!(s.contains("<redacted") && s.contains("hubname: \"VPN\""), "{}", s);
+            assert!(!s.contains("171") && !s.contains("hunter2"), "{}", s);
+        }
+
+        let welcome = Welcome { session_key: [0xAB; 20], ..sample_welcome() };
+        let s = format!("{:?}", welcome);
+        assert!(s.contains("session_key: <redacted 20 bytes>") && !s.contains("171"), "{}", s);
+        assert!(!s.contains(&welcome.session_key_32.to_string()), "{}", s);
+
+        let req = AdditionalConnect {
+            session_key: [0xAB; 20],
+            client_str: "SoftEther VPN Client".to_string(),
+            client_ver: 443,
+            client_build: 9799,
+        };
+        assert!(!format!("{:?}", req).contains("171"));
+    }
+}
@@ -0,0 +1,150 @@
+#!/usr/bin/env python3
+# gen_login_packs.py - Reference login packs for tests/protocol_test.rs
+#
+# Writes the LOGIN_* constants the ClientAuth builders are checked against.
+# This is an encoder independent of the Rust crate (its own pack writer and
+# SHA-0), so the test catches mistakes in either one:
+#
+#     python3 tools/gen_login_packs.py
+#
+# then paste the output over the LOGIN_* constants. Only the standard library
+# is used.
+#
+# The element list mirrors ClientUploadAuth() and the PackLoginWith*()
+# helpers in src/Cedar/Protocol.c of SoftEtherVPN_Stable, tag
+# v4.43-9799-beta (the CLIENT_BUILD this crate reports), with the elements
+# ClientAuth deliberately leaves out (see src/protocol.rs) removed:
+# support_bulk_on_rudp, support_hmac_on_bulk_of_rudp, support_udp_recovery,
+# the UDP acceleration parameters, pencore, and the node info / WinVer block.
+# PackAddBool is PackAddInt(0 or 1) in the C code, so flags are VALUE_INT.
+
+import struct
+
+VALUE_INT, VALUE_DATA, VALUE_STR = 0, 1, 2
+
+CLIENT_STR = b"SoftEther VPN Client"
+CLIENT_VER = 443
+CLIENT_BUILD = 9799
+
+
+def sha0(m):
+    h = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0]
+    bits = len(m) * 8
+    m += b"\x80"
+    while len(m) % 64 != 56:
+        m += b"\0"
+    m += struct.pack(">Q", bits)
+    rol = lambda x, n: ((x << n) | (x >> (32 - n))) & 0xFFFFFFFF
+    for i in range(0, len(m), 64):
+        w = list(struct.unpack(">16I", m[i:i + 64]))
+        # SHA-0: no rotate in the message schedule
+        for t in range(16, 80):
+            w.append(w[t - 3] ^ w[t - 8] ^ w[t - 14] ^ w[t - 16])
+        a, b, c, d, e = h
+        for t in range(80):
+            if t < 20:
+                f, k = (b & c) | (~b & d), 0x5A827999
+            elif t < 40:
+                f, k = b ^ c ^ d, 0x6ED9EBA1
+            elif t < 60:
+                f, k = (b & c) | (b & d) | (c & d), 0x8F1BBCDC
+            else:
+                f, k = b ^ c ^ d, 0xCA62C1D6
+            a, b, c, d, e = (rol(a, 5) + f + e + k + w[t]) & 0xFFFFFFFF, a, rol(b, 30), c, d
+        h = [(x + y) & 0xFFFFFFFF for x, y in zip(h, [a, b, c, d, e])]
+    return b"".join(struct.pack(">I", x) for x in h)
+
+
+def u32(n):
+    return struct.pack(">I", n)
+
+
+# WriteElement: name as a BUF string (length includes a terminator that isn't
+# sent), type, value count, then the single value
+def element(name, t, v):
+    b = u32(len(name) + 1) + name.encode() + u32(t) + u32(1)
+    if t == VALUE_INT:
+        return b + u32(v)
+    return b + u32(len(v)) + v
+
+
+def pack(elements):
+    return u32(len(elements)) + b"".join(element(*e) for e in elements)
+
+
+# ClientUploadAuth: PackLoginWith*() first, then the client and option fields
+def login(hubname, username, authtype, credentials):
+    return pack(
+        [
+            ("method", VALUE_STR, b"login"),
+            ("hubname", VALUE_STR, hubname),
+            ("username", VALUE_STR, username),
+            ("authtype", VALUE_INT, authtype),
+        ]
+        + credentials
+        + [
+            ("client_str", VALUE_STR, CLIENT_STR),
+            ("client_ver", VALUE_INT, CLIENT_VER),
+            ("client_build", VALUE_INT, CLIENT_BUILD),
+            ("protocol", VALUE_INT, 0),
+            ("hello", VALUE_STR, CLIENT_STR),
+            ("version", VALUE_INT, CLIENT_VER),
+            ("build", VALUE_INT, CLIENT_BUILD),
+            ("client_id", VALUE_INT, 0),
+            ("max_connection", VALUE_INT, 1),
+            ("use_encrypt", VALUE_INT, 1),
+            ("use_compress", VALUE_INT, 0),
+            ("half_connection", VALUE_INT, 0),
+            ("require_bridge_routing_mode", VALUE_INT, 0),
+            ("require_monitor_mode", VALUE_INT, 0),
+            ("qos", VALUE_INT, 1),
+            ("unique_id", VALUE_DATA, bytes(20)),
+        ]
+    )
+
+
+def rust_const(comment, name, data):
+    h = data.hex()
+    lines = [h[i:i + 88] for i in range(0, len(h), 88)]
+    body = "\\\n".join("    " + line for line in lines)
+    return '// %s\nconst %s: &str = "\\\n%s";\n' % (comment, name, body)
+
+
+def main():
+    hub, user = b"DEFAULT", b"vpn"
+    random = bytes(range(20))
+    # HashPassword: SHA-0 of the password then the upper-cased username;
+    # SecurePassword: SHA-0 of that hash then the server random
+    secure_password = sha0(sha0(b"vpn" + user.upper()) + random)
+
+    cases = [
+        ("anonymous", "LOGIN_ANONYMOUS", 0, []),
+        (
+            'password "vpn", server random 00 01 .. 13',
+            "LOGIN_PASSWORD",
+            1,
+            [("secure_password", VALUE_DATA, secure_password)],
+        ),
+        (
+            'plain password "secret"',
+            "LOGIN_PLAIN_PASSWORD",
+            2,
+            [("plain_password", VALUE_STR, b"secret")],
+        ),
+        (
+            "certificate 30 82 01 0a, signature a5 x 8",
+            "LOGIN_CERT",
+            3,
+            [("cert", VALUE_DATA, bytes([0x30, 0x82, 0x01, 0x0A])), ("sign", VALUE_DATA, bytes([0xA5] * 8))],
+        ),
+        ("ticket 5a x 20", "LOGIN_TICKET", 99, [("ticket", VALUE_DATA, bytes([0x5A] * 20))]),
+    ]
+    out = []
+    for what, name, authtype, credentials in cases:
+        comment = 'Login to hub "DEFAULT" as "vpn", ' + what
+        out.append(rust_const(comment, name, login(hub, user, authtype, credentials)))
+    print("\n".join(out), end="")
+
+
+if __name__ == "__main__":
+    main()